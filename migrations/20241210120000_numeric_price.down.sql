@@ -0,0 +1,3 @@
+-- Revert prices to floating point
+ALTER TABLE raydium_pools_prices
+    ALTER COLUMN price TYPE DOUBLE PRECISION USING price::DOUBLE PRECISION;
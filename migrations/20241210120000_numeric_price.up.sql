@@ -0,0 +1,3 @@
+-- Store prices as exact decimals
+ALTER TABLE raydium_pools_prices
+    ALTER COLUMN price TYPE NUMERIC USING price::NUMERIC;
@@ -0,0 +1,3 @@
+-- Drop the slot of stored prices
+ALTER TABLE raydium_pools_prices
+    DROP COLUMN IF EXISTS slot;
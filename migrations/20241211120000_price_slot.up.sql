@@ -0,0 +1,3 @@
+-- Record the slot of the on-chain state every price was computed from
+ALTER TABLE raydium_pools_prices
+    ADD COLUMN IF NOT EXISTS slot BIGINT;
@@ -0,0 +1,4 @@
+-- Drop price gap markers
+DROP INDEX IF EXISTS idx_price_gaps_pool_pk;
+
+DROP TABLE IF EXISTS raydium_pools_price_gaps;
@@ -0,0 +1,9 @@
+-- Slot ranges during which price updates of a pool may have been missed
+CREATE TABLE IF NOT EXISTS raydium_pools_price_gaps(
+    pool_pk BYTEA NOT NULL,
+    from_slot BIGINT,
+    to_slot BIGINT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_gaps_pool_pk ON raydium_pools_price_gaps (pool_pk);
@@ -0,0 +1,4 @@
+-- Drop decoded swaps
+DROP INDEX IF EXISTS idx_trades_pool_pk_slot;
+
+DROP TABLE IF EXISTS raydium_pools_trades;
@@ -0,0 +1,15 @@
+-- Swaps executed against the pools, decoded from the `ray_log` events of Raydium AMM v4
+CREATE TABLE IF NOT EXISTS raydium_pools_trades(
+    signature BYTEA NOT NULL,
+    instruction_index INTEGER NOT NULL,
+    pool_pk BYTEA NOT NULL,
+    slot BIGINT NOT NULL,
+    direction TEXT NOT NULL,
+    amount_in NUMERIC NOT NULL,
+    amount_out NUMERIC NOT NULL,
+    user_pk BYTEA NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (signature, instruction_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trades_pool_pk_slot ON raydium_pools_trades (pool_pk, slot);
@@ -1,8 +1,25 @@
-mod open_book;
+mod account_layout;
+pub mod amm_instruction;
+pub mod clmm;
+pub mod cpmm;
+pub mod decode;
+pub mod dlmm;
+pub mod metadata;
+pub mod mint;
+pub mod open_book;
+pub mod order_book;
+pub mod quote;
+pub mod ray_log;
+#[cfg(test)]
+pub mod test_pool;
+pub mod whirlpool;
 
 use std::sync::Arc;
 
-use crate::amm_math::open_book::{get_keys_for_market, MarketPubkeys};
+use crate::amm_math::decode::decode_amm_info;
+use crate::amm_math::metadata::PoolMetadataCache;
+use crate::amm_math::open_book::MarketPubkeys;
+use crate::models::Price;
 use crate::rpc::JsonRpcAccountReceiver;
 use arrayref::array_ref;
 use raydium_amm::math::Calculator;
@@ -13,6 +30,7 @@ use solana_program::{
     account_info::{AccountInfo, IntoAccountInfo},
     program_pack::Pack,
 };
+use solana_sdk::clock::Slot;
 use solana_sdk::pubkey::Pubkey;
 use spl_token::state::Account;
 
@@ -58,14 +76,12 @@ pub struct PoolState {
 pub async fn calc_pool_valut_amounts(
     client: Arc<JsonRpcAccountReceiverClient>,
     amm_program_key: &Pubkey,
-    amm_pool_key: &Pubkey,
     amm_keys: &AmmKeys,
     market_keys: &MarketPubkeys,
     amm: &AmmInfo,
+    min_context_slot: Option<Slot>,
 ) -> anyhow::Result<CalculateResult> {
     let load_pubkeys = vec![
-        *amm_pool_key,
-        amm_keys.amm_target,
         amm_keys.amm_pc_vault,
         amm_keys.amm_coin_vault,
         amm_keys.amm_open_order,
@@ -73,9 +89,9 @@ pub async fn calc_pool_valut_amounts(
         *market_keys.event_q,
     ];
 
-    let accounts = client.get_multiple_accounts(&load_pubkeys).await?;
-    let accounts = array_ref![accounts, 0, 7];
-    let [_, _, amm_pc_vault_account, amm_coin_vault_account, amm_open_orders_account, market_account, market_event_q_account] =
+    let accounts = client.get_multiple_accounts(&load_pubkeys, min_context_slot).await?;
+    let accounts = array_ref![accounts, 0, 5];
+    let [amm_pc_vault_account, amm_coin_vault_account, amm_open_orders_account, market_account, market_event_q_account] =
         accounts;
 
     let amm_pc_vault = Account::unpack(&amm_pc_vault_account.data)?;
@@ -149,26 +165,90 @@ pub fn load_amm_keys(amm_program_key: &Pubkey, amm_pool_key: &Pubkey, amm_info:
 
 pub async fn load_pool_state(
     client: Arc<JsonRpcAccountReceiverClient>,
-    amm_info: Vec<u8>,
+    metadata_cache: &PoolMetadataCache,
+    amm_info_owner: &Pubkey,
+    amm_info_data: &[u8],
     amm_program_key: &Pubkey,
     amm_pool_key: &Pubkey,
+    slot: Slot,
 ) -> anyhow::Result<PoolState> {
-    let amm_info = {
-        let account_data = amm_info.as_slice();
-        unsafe { &*(&account_data[0] as *const u8 as *const AmmInfo) }.clone()
-    };
-    let amm_keys = load_amm_keys(&amm_program_key, &amm_pool_key, &amm_info)?;
-    let market_keys = get_keys_for_market(client.clone(), &amm_keys.market_program, &amm_keys.market).await?;
-    let calculate_result =
-        calc_pool_valut_amounts(client.clone(), &amm_program_key, &amm_pool_key, &amm_keys, &market_keys, &amm_info)
-            .await?;
-
-    Ok(PoolState { pool: calculate_result, pool_amm_keys: amm_keys, pool_market_keys: market_keys })
+    let amm_info = decode_amm_info(amm_info_owner, amm_program_key, amm_info_data)?;
+    let metadata = metadata_cache
+        .get_or_load(client.clone(), amm_program_key, amm_pool_key, &amm_info)
+        .await?;
+    let calculate_result = calc_pool_valut_amounts(
+        client.clone(),
+        &amm_program_key,
+        &metadata.amm_keys,
+        &metadata.market_keys,
+        &amm_info,
+        Some(slot),
+    )
+    .await?;
+
+    Ok(PoolState {
+        pool: calculate_result,
+        pool_amm_keys: metadata.amm_keys,
+        pool_market_keys: metadata.market_keys,
+    })
+}
+
+pub fn calc_coin_in_pc(pool: &CalculateResult) -> anyhow::Result<Price> {
+    Price::from_amounts(
+        pool.pool_pc_vault_amount,
+        pool.pool_pc_decimals,
+        pool.pool_coin_vault_amount,
+        pool.pool_coin_decimals,
+    )
 }
 
-pub fn calc_coin_in_pc(pool: &CalculateResult) -> f64 {
-    (pool.pool_pc_vault_amount as f64)
-        / 10_f64.powf(pool.pool_pc_decimals as f64)
-        / (pool.pool_coin_vault_amount as f64)
-        * 10_f64.powf(pool.pool_coin_decimals as f64)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm_math::test_pool::*;
+    use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+    use std::str::FromStr;
+
+    async fn load_test_pool(pool: &TestPool) -> anyhow::Result<PoolState> {
+        load_pool_state(
+            pool.accounts.clone(),
+            &PoolMetadataCache::default(),
+            &RAYDIUM_AMM_V4_PROGRAM_ID,
+            &pool.amm_info_data(),
+            &RAYDIUM_AMM_V4_PROGRAM_ID,
+            &pool.pool,
+            100,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn counts_open_orders_funds_in_orderbook_mode() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let state = load_test_pool(&pool).await.unwrap();
+
+        assert_eq!(state.pool.pool_coin_vault_amount, COIN_VAULT_AMOUNT + OPEN_ORDERS_COIN_TOTAL - NEED_TAKE_PNL_COIN);
+        assert_eq!(state.pool.pool_pc_vault_amount, PC_VAULT_AMOUNT + OPEN_ORDERS_PC_TOTAL - NEED_TAKE_PNL_PC);
+        assert_eq!(calc_coin_in_pc(&state.pool).unwrap(), Price::from_str("150").unwrap());
+    }
+
+    #[tokio::test]
+    async fn counts_vaults_only_in_no_orderbook_mode() {
+        let pool = TestPool::new(NO_ORDERBOOK_STATUS);
+        let state = load_test_pool(&pool).await.unwrap();
+
+        assert_eq!(state.pool.pool_coin_vault_amount, COIN_VAULT_AMOUNT - NEED_TAKE_PNL_COIN);
+        assert_eq!(state.pool.pool_pc_vault_amount, PC_VAULT_AMOUNT - NEED_TAKE_PNL_PC);
+        assert_eq!((state.pool.swap_fee_numerator, state.pool.swap_fee_denominator), (25, 10_000));
+    }
+
+    #[test]
+    fn derives_amm_authority_from_nonce() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let amm_keys = load_amm_keys(&RAYDIUM_AMM_V4_PROGRAM_ID, &pool.pool, &pool.amm_info).unwrap();
+
+        let amm_authority = Pubkey::find_program_address(&[processor::AUTHORITY_AMM], &RAYDIUM_AMM_V4_PROGRAM_ID).0;
+        assert_eq!(amm_keys.amm_authority, amm_authority);
+        assert_eq!((amm_keys.market, amm_keys.amm_open_order), (pool.market, pool.amm_info.open_orders));
+    }
 }
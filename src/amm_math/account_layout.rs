@@ -0,0 +1,64 @@
+use anyhow::{bail, Context, Result};
+use solana_sdk::pubkey::Pubkey;
+
+pub const ANCHOR_DISCRIMINATOR_LEN: usize = 8;
+
+/// Little-endian reader over the raw data of a program account.
+pub struct AccountLayout<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> AccountLayout<'a> {
+    pub fn new(data: &'a [u8], min_len: usize) -> Result<Self> {
+        if data.len() < min_len {
+            bail!("account data is truncated: got {} bytes, expected at least {min_len}", data.len());
+        }
+
+        Ok(Self { data })
+    }
+
+    /// Checks the Anchor account discriminator and the minimal length of the account.
+    pub fn anchor(data: &'a [u8], discriminator: [u8; ANCHOR_DISCRIMINATOR_LEN], min_len: usize) -> Result<Self> {
+        let layout = Self::new(data, min_len.max(ANCHOR_DISCRIMINATOR_LEN))?;
+        if data[..ANCHOR_DISCRIMINATOR_LEN] != discriminator {
+            bail!("account discriminator mismatch");
+        }
+
+        Ok(layout)
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+        self.data
+            .get(offset..offset + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .with_context(|| format!("offset {offset} is out of account bounds"))
+    }
+
+    pub fn u8(&self, offset: usize) -> Result<u8> {
+        Ok(self.bytes::<1>(offset)?[0])
+    }
+
+    pub fn u16(&self, offset: usize) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn u32(&self, offset: usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn i32(&self, offset: usize) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn u64(&self, offset: usize) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn u128(&self, offset: usize) -> Result<u128> {
+        Ok(u128::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn pubkey(&self, offset: usize) -> Result<Pubkey> {
+        Ok(Pubkey::new_from_array(self.bytes(offset)?))
+    }
+}
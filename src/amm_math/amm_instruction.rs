@@ -0,0 +1,113 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::models::NewPool;
+use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::rpc::{ExecutedInstruction, TransactionUpdate};
+use anyhow::{ensure, Result};
+use tracing::warn;
+
+/// Tag of `AmmInstruction::Initialize2`, the instruction creating a pool.
+const INITIALIZE2_TAG: u8 = 1;
+/// Tag, nonce, open time, initial pc amount and initial coin amount.
+const INITIALIZE2_DATA_LEN: usize = 26;
+const INITIALIZE2_ACCOUNTS_LEN: usize = 21;
+
+// Accounts of `Initialize2`, the token, associated token, system and rent programs come first.
+const POOL_ACCOUNT: usize = 4;
+const LP_MINT_ACCOUNT: usize = 7;
+const COIN_MINT_ACCOUNT: usize = 8;
+const PC_MINT_ACCOUNT: usize = 9;
+const MARKET_ACCOUNT: usize = 16;
+const CREATOR_ACCOUNT: usize = 17;
+
+/// Pools created by the transaction, including the ones created through another program.
+pub fn created_pools(transaction: &TransactionUpdate) -> Vec<NewPool> {
+    transaction
+        .instructions
+        .iter()
+        .filter(|instruction| {
+            instruction.program_id == RAYDIUM_AMM_V4_PROGRAM_ID && instruction.data.first() == Some(&INITIALIZE2_TAG)
+        })
+        .filter_map(|instruction| {
+            decode_initialize2(transaction, instruction)
+                .inspect_err(|e| warn!("Malformed pool creation in '{}': {e}", transaction.signature))
+                .ok()
+        })
+        .collect()
+}
+
+fn decode_initialize2(transaction: &TransactionUpdate, instruction: &ExecutedInstruction) -> Result<NewPool> {
+    let accounts = &instruction.accounts;
+    ensure!(
+        accounts.len() >= INITIALIZE2_ACCOUNTS_LEN,
+        "got {} accounts, expected at least {INITIALIZE2_ACCOUNTS_LEN}",
+        accounts.len()
+    );
+    let data = AccountLayout::new(&instruction.data, INITIALIZE2_DATA_LEN)?;
+
+    Ok(NewPool {
+        pool_pubkey: accounts[POOL_ACCOUNT],
+        coin_mint: accounts[COIN_MINT_ACCOUNT],
+        pc_mint: accounts[PC_MINT_ACCOUNT],
+        lp_mint: accounts[LP_MINT_ACCOUNT],
+        market: accounts[MARKET_ACCOUNT],
+        creator: accounts[CREATOR_ACCOUNT],
+        open_time: data.u64(2)?,
+        init_pc_amount: data.u64(10)?,
+        init_coin_amount: data.u64(18)?,
+        signature: transaction.signature,
+        slot: transaction.slot,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signature;
+
+    fn initialize2(accounts: Vec<Pubkey>) -> ExecutedInstruction {
+        let mut data = vec![INITIALIZE2_TAG, 254];
+        for value in [1_700_000_000u64, 5_000_000, 1_000_000_000] {
+            data.extend(value.to_le_bytes());
+        }
+
+        ExecutedInstruction { program_id: RAYDIUM_AMM_V4_PROGRAM_ID, accounts, data }
+    }
+
+    fn transaction(instructions: Vec<ExecutedInstruction>) -> TransactionUpdate {
+        TransactionUpdate {
+            signature: Signature::new_unique(),
+            slot: 42,
+            account_keys: vec![],
+            instructions,
+            log_messages: vec![],
+        }
+    }
+
+    #[test]
+    fn decodes_pools_created_through_other_programs() {
+        let accounts = (0..INITIALIZE2_ACCOUNTS_LEN)
+            .map(|_| Pubkey::new_unique())
+            .collect::<Vec<_>>();
+        let router = ExecutedInstruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![1] };
+        let swap = ExecutedInstruction { program_id: RAYDIUM_AMM_V4_PROGRAM_ID, accounts: vec![], data: vec![9] };
+        let transaction = transaction(vec![router, initialize2(accounts.clone()), swap]);
+
+        let [new_pool] = created_pools(&transaction).try_into().unwrap();
+        assert_eq!((new_pool.pool_pubkey, new_pool.lp_mint), (accounts[4], accounts[7]));
+        assert_eq!((new_pool.coin_mint, new_pool.pc_mint), (accounts[8], accounts[9]));
+        assert_eq!((new_pool.market, new_pool.creator), (accounts[16], accounts[17]));
+        assert_eq!(new_pool.open_time, 1_700_000_000);
+        assert_eq!((new_pool.init_pc_amount, new_pool.init_coin_amount), (5_000_000, 1_000_000_000));
+        assert_eq!((new_pool.signature, new_pool.slot), (transaction.signature, 42));
+    }
+
+    #[test]
+    fn skips_truncated_instructions() {
+        let mut truncated = initialize2((0..INITIALIZE2_ACCOUNTS_LEN).map(|_| Pubkey::new_unique()).collect());
+        truncated.data.truncate(INITIALIZE2_DATA_LEN - 1);
+        let missing_accounts = initialize2(vec![Pubkey::new_unique(); 17]);
+
+        assert!(created_pools(&transaction(vec![truncated, missing_accounts])).is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::models::price::{powi, CALCULATION_PRECISION};
+use crate::models::Price;
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, One};
+use solana_sdk::pubkey::Pubkey;
+
+const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+const POOL_STATE_LEN: usize = 1544;
+
+const TOKEN_MINT_0_OFFSET: usize = 73;
+const TOKEN_MINT_1_OFFSET: usize = 105;
+const TOKEN_VAULT_0_OFFSET: usize = 137;
+const TOKEN_VAULT_1_OFFSET: usize = 169;
+const MINT_DECIMALS_0_OFFSET: usize = 233;
+const MINT_DECIMALS_1_OFFSET: usize = 234;
+const TICK_SPACING_OFFSET: usize = 235;
+const LIQUIDITY_OFFSET: usize = 237;
+const SQRT_PRICE_X64_OFFSET: usize = 253;
+const TICK_CURRENT_OFFSET: usize = 269;
+
+/// Fields of the Raydium CLMM `PoolState` account needed for pricing.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClmmPoolState {
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+impl ClmmPoolState {
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let layout = AccountLayout::anchor(data, POOL_STATE_DISCRIMINATOR, POOL_STATE_LEN)?;
+
+        Ok(Self {
+            token_mint_0: layout.pubkey(TOKEN_MINT_0_OFFSET)?,
+            token_mint_1: layout.pubkey(TOKEN_MINT_1_OFFSET)?,
+            token_vault_0: layout.pubkey(TOKEN_VAULT_0_OFFSET)?,
+            token_vault_1: layout.pubkey(TOKEN_VAULT_1_OFFSET)?,
+            mint_decimals_0: layout.u8(MINT_DECIMALS_0_OFFSET)?,
+            mint_decimals_1: layout.u8(MINT_DECIMALS_1_OFFSET)?,
+            tick_spacing: layout.u16(TICK_SPACING_OFFSET)?,
+            liquidity: layout.u128(LIQUIDITY_OFFSET)?,
+            sqrt_price_x64: layout.u128(SQRT_PRICE_X64_OFFSET)?,
+            tick_current: layout.i32(TICK_CURRENT_OFFSET)?,
+        })
+    }
+}
+
+/// Token amounts backing the liquidity of the tick range the pool currently trades in, UI units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InRangeLiquidity {
+    pub liquidity: u128,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount_0: BigDecimal,
+    pub amount_1: BigDecimal,
+}
+
+/// Price of token 0 in token 1, UI units.
+pub fn calc_clmm_price(pool: &ClmmPoolState) -> Price {
+    Price::from_sqrt_price_x64(pool.sqrt_price_x64, pool.mint_decimals_0, pool.mint_decimals_1)
+}
+
+pub fn calc_in_range_liquidity(pool: &ClmmPoolState) -> InRangeLiquidity {
+    let tick_spacing = pool.tick_spacing.max(1) as i32;
+    let tick_lower = pool.tick_current.div_euclid(tick_spacing) * tick_spacing;
+    let tick_upper = tick_lower + tick_spacing;
+
+    let sqrt_price_lower = tick_to_sqrt_price(tick_lower);
+    let sqrt_price_upper = tick_to_sqrt_price(tick_upper);
+    let sqrt_price = (BigDecimal::from(BigInt::from(pool.sqrt_price_x64)) / BigDecimal::from(BigInt::one() << 64u32))
+        .clamp(sqrt_price_lower.clone(), sqrt_price_upper.clone());
+    let liquidity = BigDecimal::from(BigInt::from(pool.liquidity));
+
+    let amount_0 = &liquidity * (&sqrt_price_upper - &sqrt_price) / (&sqrt_price * &sqrt_price_upper);
+    let amount_1 = &liquidity * (&sqrt_price - &sqrt_price_lower);
+
+    InRangeLiquidity {
+        liquidity: pool.liquidity,
+        tick_lower,
+        tick_upper,
+        amount_0: (amount_0 / ui_scale(pool.mint_decimals_0)).with_prec(CALCULATION_PRECISION),
+        amount_1: (amount_1 / ui_scale(pool.mint_decimals_1)).with_prec(CALCULATION_PRECISION),
+    }
+}
+
+/// `sqrt(1.0001 ^ tick)`, the square root price at the tick boundary.
+fn tick_to_sqrt_price(tick: i32) -> BigDecimal {
+    let price = powi(&BigDecimal::new(BigInt::from(10_001), 4), tick);
+    price
+        .sqrt()
+        .expect("powers of 1.0001 are positive")
+        .with_prec(CALCULATION_PRECISION)
+}
+
+/// `10 ^ decimals`, converts a raw token amount into UI units.
+fn ui_scale(decimals: u8) -> BigDecimal {
+    BigDecimal::new(BigInt::one(), -i64::from(decimals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn encoded_pool_state(sqrt_price_x64: u128, tick_current: i32, liquidity: u128) -> Vec<u8> {
+        let mut data = vec![0u8; POOL_STATE_LEN];
+        data[..8].copy_from_slice(&POOL_STATE_DISCRIMINATOR);
+        data[MINT_DECIMALS_0_OFFSET] = 9;
+        data[MINT_DECIMALS_1_OFFSET] = 6;
+        data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2].copy_from_slice(&60u16.to_le_bytes());
+        data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&liquidity.to_le_bytes());
+        data[SQRT_PRICE_X64_OFFSET..SQRT_PRICE_X64_OFFSET + 16].copy_from_slice(&sqrt_price_x64.to_le_bytes());
+        data[TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4].copy_from_slice(&tick_current.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_price_with_decimals() {
+        // Raw price of 4 with 9/6 decimals gives 4 * 10^3 in UI units.
+        let pool = ClmmPoolState::decode(&encoded_pool_state(2u128 << 64, 13_863, 1_000_000)).unwrap();
+
+        assert_eq!(pool.tick_current, 13_863);
+        assert_eq!(pool.liquidity, 1_000_000);
+        assert_eq!(calc_clmm_price(&pool), Price::from_str("4000").unwrap());
+    }
+
+    #[test]
+    fn in_range_liquidity_brackets_current_tick() {
+        let pool = ClmmPoolState::decode(&encoded_pool_state(2u128 << 64, 13_863, 1_000_000_000_000)).unwrap();
+        let in_range = calc_in_range_liquidity(&pool);
+
+        assert_eq!((in_range.tick_lower, in_range.tick_upper), (13_860, 13_920));
+        assert!(in_range.amount_0 > BigDecimal::default() && in_range.amount_1 > BigDecimal::default());
+    }
+
+    #[test]
+    fn in_range_liquidity_at_lower_tick_is_token_0_only() {
+        // Square root price of 1 is tick 0, the range [0, 60) holds L * (1 - 1 / sqrt(1.0001 ^ 60)) of token 0.
+        let pool = ClmmPoolState::decode(&encoded_pool_state(1u128 << 64, 0, 1_000_000_000_000)).unwrap();
+        let in_range = calc_in_range_liquidity(&pool);
+
+        assert_eq!(in_range.amount_1, BigDecimal::default());
+        assert_eq!(in_range.amount_0.round(6), BigDecimal::from_str("2.995355").unwrap());
+    }
+
+    #[test]
+    fn rejects_foreign_account() {
+        let mut data = encoded_pool_state(1u128 << 64, 0, 0);
+        data[0] = 0;
+
+        assert!(ClmmPoolState::decode(&data).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use crate::models::Price;
+use anyhow::{ensure, Context};
+use arrayref::array_ref;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+const POOL_STATE_LEN: usize = 637;
+
+const TOKEN_0_VAULT_OFFSET: usize = 72;
+const TOKEN_1_VAULT_OFFSET: usize = 104;
+const TOKEN_0_MINT_OFFSET: usize = 168;
+const TOKEN_1_MINT_OFFSET: usize = 200;
+const STATUS_OFFSET: usize = 329;
+const MINT_0_DECIMALS_OFFSET: usize = 331;
+const MINT_1_DECIMALS_OFFSET: usize = 332;
+const PROTOCOL_FEES_TOKEN_0_OFFSET: usize = 341;
+const PROTOCOL_FEES_TOKEN_1_OFFSET: usize = 349;
+const FUND_FEES_TOKEN_0_OFFSET: usize = 357;
+const FUND_FEES_TOKEN_1_OFFSET: usize = 365;
+
+/// `PoolStatusBitIndex` bits, a set bit disables deposits, withdrawals or swaps respectively.
+const STATUS_SWAP_DISABLED: u8 = 1 << 2;
+const KNOWN_STATUS_BITS: u8 = 0b111;
+
+/// Offset of `amount` in SPL Token and Token-2022 accounts, extensions of the latter follow the base layout.
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Fields of the Raydium CP-Swap `PoolState` account needed for pricing.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CpmmPoolState {
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    pub status: u8,
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+}
+
+impl CpmmPoolState {
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let layout = AccountLayout::anchor(data, POOL_STATE_DISCRIMINATOR, POOL_STATE_LEN)?;
+        let status = layout.u8(STATUS_OFFSET)?;
+        ensure!(status & !KNOWN_STATUS_BITS == 0, "unknown pool status {status:#b}");
+
+        Ok(Self {
+            token_0_vault: layout.pubkey(TOKEN_0_VAULT_OFFSET)?,
+            token_1_vault: layout.pubkey(TOKEN_1_VAULT_OFFSET)?,
+            token_0_mint: layout.pubkey(TOKEN_0_MINT_OFFSET)?,
+            token_1_mint: layout.pubkey(TOKEN_1_MINT_OFFSET)?,
+            status,
+            mint_0_decimals: layout.u8(MINT_0_DECIMALS_OFFSET)?,
+            mint_1_decimals: layout.u8(MINT_1_DECIMALS_OFFSET)?,
+            protocol_fees_token_0: layout.u64(PROTOCOL_FEES_TOKEN_0_OFFSET)?,
+            protocol_fees_token_1: layout.u64(PROTOCOL_FEES_TOKEN_1_OFFSET)?,
+            fund_fees_token_0: layout.u64(FUND_FEES_TOKEN_0_OFFSET)?,
+            fund_fees_token_1: layout.u64(FUND_FEES_TOKEN_1_OFFSET)?,
+        })
+    }
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status & STATUS_SWAP_DISABLED == 0
+    }
+}
+
+/// Vault balances that belong to liquidity providers, i.e. without the accrued protocol and fund fees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CpmmReserves {
+    pub token_0_amount: u64,
+    pub token_1_amount: u64,
+}
+
+pub async fn load_cpmm_reserves(
+    client: Arc<JsonRpcAccountReceiverClient>,
+    pool: &CpmmPoolState,
+    min_context_slot: Option<Slot>,
+) -> anyhow::Result<CpmmReserves> {
+    let accounts = client
+        .get_multiple_accounts(&[pool.token_0_vault, pool.token_1_vault], min_context_slot)
+        .await?;
+    let [vault_0_account, vault_1_account] = array_ref![accounts, 0, 2];
+
+    calc_cpmm_reserves(pool, token_account_amount(&vault_0_account.data)?, token_account_amount(&vault_1_account.data)?)
+}
+
+pub fn calc_cpmm_reserves(
+    pool: &CpmmPoolState,
+    vault_0_amount: u64,
+    vault_1_amount: u64,
+) -> anyhow::Result<CpmmReserves> {
+    Ok(CpmmReserves {
+        token_0_amount: vault_0_amount
+            .checked_sub(pool.protocol_fees_token_0)
+            .and_then(|amount| amount.checked_sub(pool.fund_fees_token_0))
+            .context("token 0 fees exceed vault balance")?,
+        token_1_amount: vault_1_amount
+            .checked_sub(pool.protocol_fees_token_1)
+            .and_then(|amount| amount.checked_sub(pool.fund_fees_token_1))
+            .context("token 1 fees exceed vault balance")?,
+    })
+}
+
+/// Price of token 0 in token 1, UI units.
+pub fn calc_cpmm_price(pool: &CpmmPoolState, reserves: &CpmmReserves) -> anyhow::Result<Price> {
+    Price::from_amounts(
+        reserves.token_1_amount,
+        pool.mint_1_decimals.into(),
+        reserves.token_0_amount,
+        pool.mint_0_decimals.into(),
+    )
+}
+
+fn token_account_amount(data: &[u8]) -> anyhow::Result<u64> {
+    AccountLayout::new(data, TOKEN_ACCOUNT_LEN)?.u64(TOKEN_ACCOUNT_AMOUNT_OFFSET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn put_u64(data: &mut [u8], offset: usize, value: u64) {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn encoded_pool_state() -> Vec<u8> {
+        let mut data = vec![0u8; POOL_STATE_LEN];
+        data[..8].copy_from_slice(&POOL_STATE_DISCRIMINATOR);
+        data[MINT_0_DECIMALS_OFFSET] = 9;
+        data[MINT_1_DECIMALS_OFFSET] = 6;
+        put_u64(&mut data, PROTOCOL_FEES_TOKEN_0_OFFSET, 1_000);
+        put_u64(&mut data, PROTOCOL_FEES_TOKEN_1_OFFSET, 2_000);
+        put_u64(&mut data, FUND_FEES_TOKEN_0_OFFSET, 500);
+        put_u64(&mut data, FUND_FEES_TOKEN_1_OFFSET, 700);
+        data
+    }
+
+    #[test]
+    fn excludes_fees_from_reserves() {
+        let pool = CpmmPoolState::decode(&encoded_pool_state()).unwrap();
+        let reserves = calc_cpmm_reserves(&pool, 2_000_001_500, 300_002_700).unwrap();
+
+        assert_eq!(reserves, CpmmReserves { token_0_amount: 2_000_000_000, token_1_amount: 300_000_000 });
+        assert_eq!(calc_cpmm_price(&pool, &reserves).unwrap(), Price::from_str("150").unwrap());
+    }
+
+    #[test]
+    fn rejects_fees_above_balance() {
+        let pool = CpmmPoolState::decode(&encoded_pool_state()).unwrap();
+
+        assert!(calc_cpmm_reserves(&pool, 1_000, 300_002_700).is_err());
+    }
+
+    #[test]
+    fn checks_status_bits() {
+        let mut data = encoded_pool_state();
+        assert!(CpmmPoolState::decode(&data).unwrap().swap_enabled());
+
+        data[STATUS_OFFSET] = STATUS_SWAP_DISABLED | 1;
+        assert!(!CpmmPoolState::decode(&data).unwrap().swap_enabled());
+
+        data[STATUS_OFFSET] = 1 << 3;
+        assert!(CpmmPoolState::decode(&data).is_err());
+    }
+
+    #[test]
+    fn reads_token_account_amount() {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        put_u64(&mut data, TOKEN_ACCOUNT_AMOUNT_OFFSET, 42);
+
+        assert_eq!(token_account_amount(&data).unwrap(), 42);
+    }
+}
@@ -0,0 +1,98 @@
+use raydium_amm::state::AmmInfo;
+use safe_transmute::to_bytes::transmute_one_to_bytes_mut;
+use solana_sdk::pubkey::Pubkey;
+use std::mem::size_of;
+use thiserror::Error;
+
+/// Highest status value known to `AmmStatus` (`WaitingTrade`).
+const MAX_AMM_STATUS: u64 = 7;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AmmInfoDecodeError {
+    #[error("account is owned by '{actual}', expected AMM program '{expected}'")]
+    WrongOwner { expected: Pubkey, actual: Pubkey },
+    #[error("account data is truncated: got {actual} bytes, expected at least {expected}")]
+    Truncated { expected: usize, actual: usize },
+    #[error("AMM is not initialized")]
+    Uninitialized,
+    #[error("unknown AMM status {0}")]
+    UnknownStatus(u64),
+}
+
+/// Decodes `AmmInfo` from raw account data without assuming anything about the buffer.
+///
+/// The bytes are copied into an owned `AmmInfo`, so the alignment of `data` does not matter.
+pub fn decode_amm_info(owner: &Pubkey, amm_program_key: &Pubkey, data: &[u8]) -> Result<AmmInfo, AmmInfoDecodeError> {
+    if owner != amm_program_key {
+        return Err(AmmInfoDecodeError::WrongOwner { expected: *amm_program_key, actual: *owner });
+    }
+
+    let expected_len = size_of::<AmmInfo>();
+    if data.len() < expected_len {
+        return Err(AmmInfoDecodeError::Truncated { expected: expected_len, actual: data.len() });
+    }
+
+    let mut amm_info = AmmInfo::default();
+    transmute_one_to_bytes_mut(&mut amm_info).copy_from_slice(&data[..expected_len]);
+
+    match amm_info.status {
+        0 => Err(AmmInfoDecodeError::Uninitialized),
+        status if status > MAX_AMM_STATUS => Err(AmmInfoDecodeError::UnknownStatus(status)),
+        _ => Ok(amm_info),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use safe_transmute::to_bytes::transmute_one_to_bytes;
+
+    fn encoded_amm_info(status: u64) -> Vec<u8> {
+        let amm_info = AmmInfo { status, ..AmmInfo::default() };
+        transmute_one_to_bytes(&amm_info).to_vec()
+    }
+
+    #[test]
+    fn decodes_valid_account() {
+        let program = Pubkey::new_unique();
+        let amm_info = decode_amm_info(&program, &program, &encoded_amm_info(6)).unwrap();
+
+        assert_eq!(amm_info.status, 6);
+    }
+
+    #[test]
+    fn decodes_misaligned_buffer() {
+        let program = Pubkey::new_unique();
+        let mut data = vec![0u8];
+        data.extend(encoded_amm_info(6));
+
+        assert_eq!(decode_amm_info(&program, &program, &data[1..]).unwrap().status, 6);
+    }
+
+    #[test]
+    fn rejects_wrong_owner() {
+        let (program, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let err = decode_amm_info(&owner, &program, &encoded_amm_info(6)).unwrap_err();
+
+        assert_eq!(err, AmmInfoDecodeError::WrongOwner { expected: program, actual: owner });
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let program = Pubkey::new_unique();
+        let data = encoded_amm_info(6);
+        let err = decode_amm_info(&program, &program, &data[..100]).unwrap_err();
+
+        assert_eq!(err, AmmInfoDecodeError::Truncated { expected: data.len(), actual: 100 });
+    }
+
+    #[test]
+    fn rejects_uninitialized_amm() {
+        let program = Pubkey::new_unique();
+
+        assert_eq!(
+            decode_amm_info(&program, &program, &encoded_amm_info(0)).unwrap_err(),
+            AmmInfoDecodeError::Uninitialized
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::models::Price;
+use solana_sdk::pubkey::Pubkey;
+
+const LB_PAIR_DISCRIMINATOR: [u8; 8] = [33, 11, 49, 98, 181, 101, 177, 13];
+const LB_PAIR_LEN: usize = 904;
+
+const ACTIVE_ID_OFFSET: usize = 76;
+const BIN_STEP_OFFSET: usize = 80;
+const TOKEN_X_MINT_OFFSET: usize = 88;
+const TOKEN_Y_MINT_OFFSET: usize = 120;
+const RESERVE_X_OFFSET: usize = 152;
+const RESERVE_Y_OFFSET: usize = 184;
+
+/// Fields of the Meteora DLMM `LbPair` account needed for pricing.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LbPairState {
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+}
+
+impl LbPairState {
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let layout = AccountLayout::anchor(data, LB_PAIR_DISCRIMINATOR, LB_PAIR_LEN)?;
+
+        Ok(Self {
+            active_id: layout.i32(ACTIVE_ID_OFFSET)?,
+            bin_step: layout.u16(BIN_STEP_OFFSET)?,
+            token_x_mint: layout.pubkey(TOKEN_X_MINT_OFFSET)?,
+            token_y_mint: layout.pubkey(TOKEN_Y_MINT_OFFSET)?,
+            reserve_x: layout.pubkey(RESERVE_X_OFFSET)?,
+            reserve_y: layout.pubkey(RESERVE_Y_OFFSET)?,
+        })
+    }
+}
+
+/// Price of token X in token Y inside the active bin, UI units.
+pub fn calc_active_bin_price(lb_pair: &LbPairState, decimals_x: u8, decimals_y: u8) -> Price {
+    Price::from_bin_id(lb_pair.active_id, lb_pair.bin_step, decimals_x, decimals_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn encoded_lb_pair(active_id: i32, bin_step: u16) -> Vec<u8> {
+        let mut data = vec![0u8; LB_PAIR_LEN];
+        data[..8].copy_from_slice(&LB_PAIR_DISCRIMINATOR);
+        data[ACTIVE_ID_OFFSET..ACTIVE_ID_OFFSET + 4].copy_from_slice(&active_id.to_le_bytes());
+        data[BIN_STEP_OFFSET..BIN_STEP_OFFSET + 2].copy_from_slice(&bin_step.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn active_bin_zero_is_parity() {
+        let lb_pair = LbPairState::decode(&encoded_lb_pair(0, 25)).unwrap();
+
+        assert_eq!(calc_active_bin_price(&lb_pair, 6, 6), Price::from_str("1").unwrap());
+        assert_eq!(calc_active_bin_price(&lb_pair, 9, 6), Price::from_str("1000").unwrap());
+    }
+
+    #[test]
+    fn negative_bins_are_below_parity() {
+        let lb_pair = LbPairState::decode(&encoded_lb_pair(-1, 2_500)).unwrap();
+
+        assert_eq!(lb_pair.active_id, -1);
+        assert_eq!(calc_active_bin_price(&lb_pair, 6, 6), Price::from_str("0.8").unwrap());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::amm_math::open_book::{get_keys_for_market, MarketPubkeys};
+use crate::amm_math::{load_amm_keys, AmmKeys, JsonRpcAccountReceiverClient};
+use raydium_amm::state::AmmInfo;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Pool data that only changes when the AMM is migrated to another market.
+#[derive(Clone, Debug)]
+pub struct PoolMetadata {
+    pub amm_keys: AmmKeys,
+    pub market_keys: MarketPubkeys,
+}
+
+impl PoolMetadata {
+    fn is_valid_for(&self, amm_info: &AmmInfo) -> bool {
+        self.amm_keys.market == amm_info.market && self.amm_keys.market_program == amm_info.market_program
+    }
+}
+
+#[derive(Default)]
+pub struct PoolMetadataCache {
+    metadata: RwLock<HashMap<Pubkey, PoolMetadata>>,
+}
+
+impl PoolMetadataCache {
+    pub async fn get_or_load(
+        &self,
+        client: Arc<JsonRpcAccountReceiverClient>,
+        amm_program_key: &Pubkey,
+        amm_pool_key: &Pubkey,
+        amm_info: &AmmInfo,
+    ) -> anyhow::Result<PoolMetadata> {
+        if let Some(metadata) = self.metadata.read().await.get(amm_pool_key) {
+            if metadata.is_valid_for(amm_info) {
+                return Ok(metadata.clone());
+            }
+
+            info!("Market of pool '{amm_pool_key}' changed, reloading its metadata");
+        }
+
+        let amm_keys = load_amm_keys(amm_program_key, amm_pool_key, amm_info)?;
+        let market_keys = get_keys_for_market(client, &amm_keys.market_program, &amm_keys.market).await?;
+        let metadata = PoolMetadata { amm_keys, market_keys };
+
+        self.metadata.write().await.insert(*amm_pool_key, metadata.clone());
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm_math::test_pool::{TestPool, ORDERBOOK_STATUS};
+    use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+    use solana_sdk::account::Account;
+
+    async fn load(cache: &PoolMetadataCache, pool: &TestPool, amm_info: &AmmInfo) -> anyhow::Result<PoolMetadata> {
+        cache
+            .get_or_load(pool.accounts.clone(), &RAYDIUM_AMM_V4_PROGRAM_ID, &pool.pool, amm_info)
+            .await
+    }
+
+    #[tokio::test]
+    async fn reuses_metadata_while_the_market_is_unchanged() {
+        let (pool, cache) = (TestPool::new(ORDERBOOK_STATUS), PoolMetadataCache::default());
+        load(&cache, &pool, &pool.amm_info).await.unwrap();
+        // Reading the market again would fail from now on.
+        pool.accounts.insert(pool.market, Account::default());
+
+        let metadata = load(&cache, &pool, &pool.amm_info).await.unwrap();
+        assert_eq!(*metadata.market_keys.event_q, pool.event_q);
+    }
+
+    #[tokio::test]
+    async fn reloads_metadata_when_the_market_changes() {
+        let (pool, cache) = (TestPool::new(ORDERBOOK_STATUS), PoolMetadataCache::default());
+        load(&cache, &pool, &pool.amm_info).await.unwrap();
+        let other = TestPool::new(ORDERBOOK_STATUS);
+        pool.accounts
+            .insert(other.market, other.accounts.get_account(&other.market, None).await.unwrap());
+
+        let mut amm_info = pool.amm_info;
+        (amm_info.market, amm_info.market_program) = (other.market, other.market_program);
+        let metadata = load(&cache, &pool, &amm_info).await.unwrap();
+        assert_eq!((metadata.amm_keys.market, *metadata.market_keys.event_q), (other.market, other.event_q));
+    }
+
+    #[tokio::test]
+    async fn reloads_metadata_when_the_market_program_changes() {
+        let (pool, cache) = (TestPool::new(ORDERBOOK_STATUS), PoolMetadataCache::default());
+        load(&cache, &pool, &pool.amm_info).await.unwrap();
+        pool.accounts.insert(pool.market, Account::default());
+
+        let mut amm_info = pool.amm_info;
+        amm_info.market_program = Pubkey::new_unique();
+        assert!(load(&cache, &pool, &amm_info).await.is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Offset of `decimals` in SPL Token and Token-2022 mints.
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_LEN: usize = 82;
+
+pub fn mint_decimals(data: &[u8]) -> anyhow::Result<u8> {
+    AccountLayout::new(data, MINT_LEN)?.u8(MINT_DECIMALS_OFFSET)
+}
+
+/// Decimals of a mint never change, so they are fetched once per mint.
+#[derive(Default)]
+pub struct MintDecimalsCache {
+    decimals: RwLock<HashMap<Pubkey, u8>>,
+}
+
+impl MintDecimalsCache {
+    /// Mints that aren't cached yet are read at `min_context_slot` or later, the same as the pool they belong to.
+    pub async fn get(
+        &self,
+        client: Arc<JsonRpcAccountReceiverClient>,
+        mints: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let missing_mints = {
+            let decimals = self.decimals.read().await;
+            mints
+                .iter()
+                .filter(|mint| !decimals.contains_key(mint))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        if !missing_mints.is_empty() {
+            let accounts = client.get_multiple_accounts(&missing_mints, min_context_slot).await?;
+            let mut decimals = self.decimals.write().await;
+            for (mint, account) in missing_mints.iter().zip(accounts) {
+                decimals.insert(*mint, mint_decimals(&account.data)?);
+            }
+        }
+
+        let decimals = self.decimals.read().await;
+        Ok(mints.iter().map(|mint| decimals[mint]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_mint_decimals() {
+        let mut data = vec![0u8; MINT_LEN];
+        data[MINT_DECIMALS_OFFSET] = 6;
+
+        assert_eq!(mint_decimals(&data).unwrap(), 6);
+        assert!(mint_decimals(&data[..MINT_DECIMALS_OFFSET]).is_err());
+    }
+}
@@ -29,7 +29,7 @@ pub struct MarketPubkeys {
     pub pc_lot_size: u64,
 }
 
-fn remove_dex_account_padding<'a>(data: &'a [u8]) -> Result<Cow<'a, [u64]>> {
+pub(super) fn remove_dex_account_padding<'a>(data: &'a [u8]) -> Result<Cow<'a, [u64]>> {
     use serum_dex::state::{ACCOUNT_HEAD_PADDING, ACCOUNT_TAIL_PADDING};
     let head = &data[..ACCOUNT_HEAD_PADDING.len()];
     if data.len() < ACCOUNT_HEAD_PADDING.len() + ACCOUNT_TAIL_PADDING.len() {
@@ -93,3 +93,35 @@ pub async fn get_keys_for_market<'a>(
         pc_lot_size: market_state.pc_lot_size,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm_math::test_pool::{TestPool, ORDERBOOK_STATUS};
+    use crate::rpc::in_memory::InMemoryAccountReceiver;
+
+    #[tokio::test]
+    async fn reads_market_keys() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let market_keys = get_keys_for_market(pool.accounts.clone(), &pool.market_program, &pool.market)
+            .await
+            .unwrap();
+
+        let vault_signer_key =
+            gen_vault_signer_key(pool.vault_signer_nonce, &pool.market, &pool.market_program).unwrap();
+        assert_eq!((*market_keys.market, *market_keys.event_q), (pool.market, pool.event_q));
+        assert_eq!(*market_keys.vault_signer_key, vault_signer_key);
+        assert_eq!(*market_keys.coin_mint, pool.amm_info.coin_vault_mint);
+        assert_eq!((market_keys.coin_lot_size, market_keys.pc_lot_size), (1_000_000, 100));
+    }
+
+    #[tokio::test]
+    async fn rejects_accounts_without_dex_padding() {
+        let market = Pubkey::new_unique();
+        let accounts = InMemoryAccountReceiver::default();
+        accounts.insert(market, solana_sdk::account::Account { data: vec![0; 388], ..Default::default() });
+
+        let result = get_keys_for_market(Arc::new(accounts), &Pubkey::new_unique(), &market).await;
+        assert!(result.is_err());
+    }
+}
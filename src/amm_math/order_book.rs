@@ -0,0 +1,208 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::amm_math::decode::decode_amm_info;
+use crate::amm_math::open_book::{get_keys_for_market, remove_dex_account_padding, MarketPubkeys};
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use crate::models::Price;
+use anyhow::{bail, Result};
+use arrayref::array_ref;
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::BigDecimal;
+use safe_transmute::to_bytes::transmute_to_bytes;
+use serum_dex::state::AccountFlag;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const ACCOUNT_FLAGS_LEN: usize = 8;
+const SLAB_HEADER_LEN: usize = 32;
+const SLAB_BUMP_INDEX_OFFSET: usize = ACCOUNT_FLAGS_LEN;
+const SLAB_NODES_OFFSET: usize = ACCOUNT_FLAGS_LEN + SLAB_HEADER_LEN;
+const SLAB_NODE_LEN: usize = 72;
+
+const LEAF_NODE_TAG: u32 = 2;
+const LEAF_KEY_OFFSET: usize = 8;
+const LEAF_QUANTITY_OFFSET: usize = 56;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct L2Level {
+    pub price: Price,
+    /// Coin token, UI units.
+    pub quantity: BigDecimal,
+}
+
+/// Aggregated OpenBook orders, bids from the best (highest) price down, asks from the best (lowest) price up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct L2Book {
+    pub bids: Vec<L2Level>,
+    pub asks: Vec<L2Level>,
+}
+
+impl L2Book {
+    pub fn best_bid(&self) -> Option<&Price> {
+        self.bids.first().map(|level| &level.price)
+    }
+
+    pub fn best_ask(&self) -> Option<&Price> {
+        self.asks.first().map(|level| &level.price)
+    }
+
+    pub fn mid(&self) -> Option<Price> {
+        Some(self.best_bid()?.midpoint(self.best_ask()?))
+    }
+
+    pub fn spread(&self) -> Option<Price> {
+        Some(self.best_ask()? - self.best_bid()?)
+    }
+}
+
+/// Converts order book lots into UI units of the market tokens.
+pub struct LotConversion {
+    coin_lot_size: u64,
+    pc_lot_size: u64,
+    coin_decimals: u64,
+    pc_decimals: u64,
+}
+
+impl LotConversion {
+    pub fn new(market_keys: &MarketPubkeys, coin_decimals: u64, pc_decimals: u64) -> Result<Self> {
+        if market_keys.coin_lot_size == 0 || market_keys.pc_lot_size == 0 {
+            bail!("market has zero lot size");
+        }
+
+        Ok(Self {
+            coin_lot_size: market_keys.coin_lot_size,
+            pc_lot_size: market_keys.pc_lot_size,
+            coin_decimals,
+            pc_decimals,
+        })
+    }
+
+    fn price(&self, price_lots: u64) -> Price {
+        let pc = BigDecimal::new(BigInt::from(price_lots) * self.pc_lot_size, self.pc_decimals as i64);
+        let coin = BigDecimal::new(BigInt::from(self.coin_lot_size), self.coin_decimals as i64);
+
+        Price::new(pc / coin)
+    }
+
+    fn quantity(&self, quantity_lots: u64) -> BigDecimal {
+        BigDecimal::new(BigInt::from(quantity_lots) * self.coin_lot_size, self.coin_decimals as i64)
+    }
+}
+
+pub async fn load_order_book(
+    client: Arc<JsonRpcAccountReceiverClient>,
+    amm_program_key: &Pubkey,
+    amm_pool_key: &Pubkey,
+) -> Result<L2Book> {
+    let amm_account = client.get_account(amm_pool_key, None).await?;
+    let amm_info = decode_amm_info(&amm_account.owner, amm_program_key, &amm_account.data)?;
+    let market_keys = get_keys_for_market(client.clone(), &amm_info.market_program, &amm_info.market).await?;
+
+    let accounts = client
+        .get_multiple_accounts(&[*market_keys.bids, *market_keys.asks], None)
+        .await?;
+    let [bids_account, asks_account] = array_ref![accounts, 0, 2];
+    let lot_conversion = LotConversion::new(&market_keys, amm_info.coin_decimals, amm_info.pc_decimals)?;
+
+    decode_l2_book(&bids_account.data, &asks_account.data, &lot_conversion)
+}
+
+pub fn decode_l2_book(bids_data: &[u8], asks_data: &[u8], lot_conversion: &LotConversion) -> Result<L2Book> {
+    let to_level = |(price_lots, quantity_lots): (&u64, &u64)| L2Level {
+        price: lot_conversion.price(*price_lots),
+        quantity: lot_conversion.quantity(*quantity_lots),
+    };
+
+    Ok(L2Book {
+        bids: decode_slab(bids_data, AccountFlag::Bids)?
+            .iter()
+            .rev()
+            .map(to_level)
+            .collect(),
+        asks: decode_slab(asks_data, AccountFlag::Asks)?
+            .iter()
+            .map(to_level)
+            .collect(),
+    })
+}
+
+/// Reads every leaf of a bids/asks critbit slab and sums the order quantities per price, both in lots.
+fn decode_slab(data: &[u8], side: AccountFlag) -> Result<BTreeMap<u64, u64>> {
+    let words = remove_dex_account_padding(data)?;
+    let layout = AccountLayout::new(transmute_to_bytes(&words), SLAB_NODES_OFFSET)?;
+
+    let account_flags = layout.u64(0)?;
+    let expected_flags = AccountFlag::Initialized as u64 | side as u64;
+    if account_flags & expected_flags != expected_flags {
+        bail!("account is not an initialized {side:?} slab");
+    }
+
+    let mut levels = BTreeMap::new();
+    let bump_index = layout.u64(SLAB_BUMP_INDEX_OFFSET)? as usize;
+    for node_offset in (0..bump_index).map(|index| SLAB_NODES_OFFSET + index * SLAB_NODE_LEN) {
+        if layout.u32(node_offset)? != LEAF_NODE_TAG {
+            continue;
+        }
+
+        let price_lots = (layout.u128(node_offset + LEAF_KEY_OFFSET)? >> 64) as u64;
+        let quantity_lots = layout.u64(node_offset + LEAF_QUANTITY_OFFSET)?;
+        *levels.entry(price_lots).or_insert(0) += quantity_lots;
+    }
+
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const FREE_NODE_TAG: u32 = 3;
+
+    fn encoded_slab(side: AccountFlag, nodes: &[(u32, u64, u64)]) -> Vec<u8> {
+        let mut inner = vec![0u8; SLAB_NODES_OFFSET + nodes.len() * SLAB_NODE_LEN];
+        inner[..8].copy_from_slice(&(AccountFlag::Initialized as u64 | side as u64).to_le_bytes());
+        inner[SLAB_BUMP_INDEX_OFFSET..SLAB_BUMP_INDEX_OFFSET + 8].copy_from_slice(&(nodes.len() as u64).to_le_bytes());
+        for (index, (tag, price_lots, quantity_lots)) in nodes.iter().enumerate() {
+            let node = &mut inner[SLAB_NODES_OFFSET + index * SLAB_NODE_LEN..][..SLAB_NODE_LEN];
+            node[..4].copy_from_slice(&tag.to_le_bytes());
+            node[LEAF_KEY_OFFSET..LEAF_KEY_OFFSET + 16].copy_from_slice(&((*price_lots as u128) << 64).to_le_bytes());
+            node[LEAF_QUANTITY_OFFSET..LEAF_QUANTITY_OFFSET + 8].copy_from_slice(&quantity_lots.to_le_bytes());
+        }
+
+        [b"serum".as_slice(), &inner, b"padding".as_slice()].concat()
+    }
+
+    fn lot_conversion() -> LotConversion {
+        LotConversion { coin_lot_size: 1_000_000, pc_lot_size: 10, coin_decimals: 9, pc_decimals: 6 }
+    }
+
+    #[test]
+    fn aggregates_leaves_into_levels() {
+        let bids = encoded_slab(
+            AccountFlag::Bids,
+            &[
+                (LEAF_NODE_TAG, 100, 5),
+                (LEAF_NODE_TAG, 90, 1),
+                (FREE_NODE_TAG, 95, 7),
+                (LEAF_NODE_TAG, 100, 3),
+            ],
+        );
+        let asks = encoded_slab(AccountFlag::Asks, &[(LEAF_NODE_TAG, 110, 2)]);
+        let book = decode_l2_book(&bids, &asks, &lot_conversion()).unwrap();
+
+        assert_eq!(book.bids.len(), 2);
+        let best_bid_quantity = BigDecimal::from_str("0.008").unwrap();
+        assert_eq!(book.bids[0], L2Level { price: Price::from_str("1").unwrap(), quantity: best_bid_quantity });
+        assert_eq!(book.best_ask(), Some(&Price::from_str("1.1").unwrap()));
+        assert_eq!(book.mid(), Some(Price::from_str("1.05").unwrap()));
+        assert_eq!(book.spread(), Some(Price::from_str("0.1").unwrap()));
+    }
+
+    #[test]
+    fn rejects_wrong_side() {
+        let asks = encoded_slab(AccountFlag::Asks, &[]);
+
+        assert!(decode_l2_book(&asks, &asks, &lot_conversion()).is_err());
+    }
+}
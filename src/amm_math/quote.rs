@@ -0,0 +1,211 @@
+use crate::amm_math::{CalculateResult, PoolState};
+use crate::models::Price;
+use anyhow::{bail, Context};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, One};
+use raydium_amm::math::{Calculator, CheckedCeilDiv, SwapDirection as RaydiumSwapDirection, U128};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum SwapDirection {
+    CoinToPc,
+    PcToCoin,
+}
+
+impl SwapDirection {
+    fn to_raydium(self) -> RaydiumSwapDirection {
+        match self {
+            SwapDirection::CoinToPc => RaydiumSwapDirection::Coin2PC,
+            SwapDirection::PcToCoin => RaydiumSwapDirection::PC2Coin,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapAmount {
+    ExactIn(u64),
+    ExactOut(u64),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct SwapQuote {
+    pub direction: SwapDirection,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    /// Price of the trade in pc per coin, UI units, fee included.
+    pub effective_price: Price,
+    /// Relative loss against the spot price caused by the trade size alone, fee excluded.
+    pub price_impact: BigDecimal,
+}
+
+impl Display for SwapQuote {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sold = match self.direction {
+            SwapDirection::CoinToPc => "coin",
+            SwapDirection::PcToCoin => "pc",
+        };
+
+        write!(
+            f,
+            "{amount_in} {sold} in for {amount_out} out, fee {fee}, effective price {effective_price}, \
+             price impact {price_impact}%.",
+            amount_in = self.amount_in,
+            amount_out = self.amount_out,
+            fee = self.fee,
+            effective_price = self.effective_price,
+            price_impact = (&self.price_impact * BigDecimal::from(100)).round(4).normalized(),
+        )
+    }
+}
+
+pub fn quote_swap(pool_state: &PoolState, direction: SwapDirection, amount: SwapAmount) -> anyhow::Result<SwapQuote> {
+    calc_swap(&pool_state.pool, direction, amount)
+}
+
+/// Mirrors `swap_base_in` / `swap_base_out` of the Raydium AMM processor, so the amounts match the on-chain result.
+pub fn calc_swap(pool: &CalculateResult, direction: SwapDirection, amount: SwapAmount) -> anyhow::Result<SwapQuote> {
+    if pool.pool_pc_vault_amount == 0 || pool.pool_coin_vault_amount == 0 {
+        bail!("pool has no liquidity");
+    }
+    if pool.swap_fee_numerator >= pool.swap_fee_denominator {
+        bail!("invalid swap fee {}/{}", pool.swap_fee_numerator, pool.swap_fee_denominator);
+    }
+
+    let (reserve_in, reserve_out) = match direction {
+        SwapDirection::CoinToPc => (pool.pool_coin_vault_amount, pool.pool_pc_vault_amount),
+        SwapDirection::PcToCoin => (pool.pool_pc_vault_amount, pool.pool_coin_vault_amount),
+    };
+
+    let (amount_in, amount_in_without_fee, amount_out) = match amount {
+        SwapAmount::ExactIn(0) | SwapAmount::ExactOut(0) => bail!("swap amount must be positive"),
+        SwapAmount::ExactIn(amount_in) => {
+            let swap_fee = U128::from(amount_in)
+                .checked_mul(pool.swap_fee_numerator.into())
+                .and_then(|fee| fee.checked_ceil_div(pool.swap_fee_denominator.into()))
+                .context("swap fee overflow")?
+                .0;
+            let swap_in_after_deduct_fee = U128::from(amount_in) - swap_fee;
+            let amount_out = Calculator::swap_token_amount_base_in(
+                swap_in_after_deduct_fee,
+                pool.pool_pc_vault_amount.into(),
+                pool.pool_coin_vault_amount.into(),
+                direction.to_raydium(),
+            );
+
+            (amount_in, swap_in_after_deduct_fee.as_u64(), amount_out.as_u64())
+        }
+        SwapAmount::ExactOut(amount_out) => {
+            if amount_out >= reserve_out {
+                bail!("requested amount {amount_out} exceeds pool reserve {reserve_out}");
+            }
+
+            let swap_in_before_add_fee = Calculator::swap_token_amount_base_out(
+                amount_out.into(),
+                pool.pool_pc_vault_amount.into(),
+                pool.pool_coin_vault_amount.into(),
+                direction.to_raydium(),
+            );
+            let swap_in_after_add_fee = swap_in_before_add_fee
+                .checked_mul(pool.swap_fee_denominator.into())
+                .and_then(|amount| {
+                    amount.checked_ceil_div((pool.swap_fee_denominator - pool.swap_fee_numerator).into())
+                })
+                .context("swap amount overflow")?
+                .0;
+            if swap_in_after_add_fee > U128::from(u64::MAX) {
+                bail!("required input amount does not fit into u64");
+            }
+
+            (swap_in_after_add_fee.as_u64(), swap_in_before_add_fee.as_u64(), amount_out)
+        }
+    };
+
+    if amount_in_without_fee == 0 {
+        bail!("swap amount is consumed by the fee");
+    }
+    if amount_out == 0 {
+        bail!("swap output rounds to zero");
+    }
+
+    let (coin_amount, pc_amount) = match direction {
+        SwapDirection::CoinToPc => (amount_in, amount_out),
+        SwapDirection::PcToCoin => (amount_out, amount_in),
+    };
+    let effective_price = Price::from_amounts(pc_amount, pool.pool_pc_decimals, coin_amount, pool.pool_coin_decimals)?;
+
+    // 1 - (amount_out / amount_in_without_fee) / (reserve_out / reserve_in)
+    let execution = BigInt::from(amount_out) * reserve_in;
+    let spot = BigInt::from(amount_in_without_fee) * reserve_out;
+    let price_impact = BigDecimal::one() - BigDecimal::from(execution) / BigDecimal::from(spot);
+
+    Ok(SwapQuote {
+        direction,
+        amount_in,
+        amount_out,
+        fee: amount_in - amount_in_without_fee,
+        effective_price,
+        price_impact,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn test_pool() -> CalculateResult {
+        CalculateResult {
+            pool_pc_vault_amount: 50_000_000_000,
+            pool_pc_decimals: 6,
+            pool_coin_vault_amount: 1_000_000_000,
+            pool_coin_decimals: 9,
+            pool_lp_amount: 0,
+            swap_fee_numerator: 25,
+            swap_fee_denominator: 10_000,
+        }
+    }
+
+    #[test]
+    fn exact_in_coin_to_pc() {
+        let quote = calc_swap(&test_pool(), SwapDirection::CoinToPc, SwapAmount::ExactIn(1_000_000)).unwrap();
+
+        assert_eq!(quote.amount_in, 1_000_000);
+        assert_eq!(quote.fee, 2_500);
+        assert_eq!(quote.amount_out, 49_825_299);
+        assert_eq!(quote.effective_price, Price::from_str("49825.299").unwrap());
+        assert!(quote.price_impact > BigDecimal::default());
+        assert!(quote.price_impact < BigDecimal::new(BigInt::one(), 3));
+    }
+
+    #[test]
+    fn exact_out_pc_to_coin() {
+        let quote = calc_swap(&test_pool(), SwapDirection::PcToCoin, SwapAmount::ExactOut(1_000_000)).unwrap();
+
+        assert_eq!(quote.amount_out, 1_000_000);
+        assert_eq!(quote.amount_in, 50_175_490);
+        assert_eq!(quote.fee, 125_439);
+        assert_eq!(quote.effective_price, Price::from_str("50175.49").unwrap());
+    }
+
+    #[test]
+    fn rejects_out_amount_above_reserve() {
+        let pool = test_pool();
+        let amount = SwapAmount::ExactOut(pool.pool_coin_vault_amount);
+
+        assert!(calc_swap(&pool, SwapDirection::PcToCoin, amount).is_err());
+    }
+
+    #[test]
+    fn rejects_zero_amount() {
+        assert!(calc_swap(&test_pool(), SwapDirection::CoinToPc, SwapAmount::ExactIn(0)).is_err());
+    }
+
+    #[test]
+    fn rejects_output_rounding_to_zero() {
+        let error = calc_swap(&test_pool(), SwapDirection::PcToCoin, SwapAmount::ExactIn(10)).unwrap_err();
+
+        assert_eq!(error.to_string(), "swap output rounds to zero");
+    }
+}
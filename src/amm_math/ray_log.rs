@@ -0,0 +1,240 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::models::{Trade, TradeDirection};
+use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::rpc::{ExecutedInstruction, TransactionUpdate};
+use anyhow::{bail, ensure, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::NaiveDateTime;
+use tracing::warn;
+
+const RAY_LOG_PREFIX: &str = "Program log: ray_log: ";
+
+/// `LogType::SwapBaseIn` and `LogType::SwapBaseOut`, the other events are pool creations, deposits and withdrawals.
+const SWAP_BASE_IN_LOG: u8 = 3;
+const SWAP_BASE_OUT_LOG: u8 = 4;
+/// Log type followed by seven `u64` fields.
+const SWAP_LOG_LEN: usize = 57;
+
+/// Tags of the instructions emitting `SwapBaseIn` and `SwapBaseOut` logs.
+const SWAP_BASE_IN_TAG: u8 = 9;
+const SWAP_BASE_OUT_TAG: u8 = 11;
+/// Swaps without the target orders account take 17 accounts, the pool comes second and the trader last either way.
+const SWAP_MIN_ACCOUNTS: usize = 17;
+const POOL_ACCOUNT: usize = 1;
+
+/// `SwapDirection::Coin2PC` and `SwapDirection::PC2Coin`.
+const COIN_TO_PC: u64 = 1;
+const PC_TO_COIN: u64 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+struct SwapLog {
+    log_type: u8,
+    direction: TradeDirection,
+    amount_in: u64,
+    amount_out: u64,
+}
+
+/// Swaps logged by the AMM program, each one is attributed to the pool of the instruction that logged it.
+pub fn trades(transaction: &TransactionUpdate, recorded_at: NaiveDateTime) -> Vec<Trade> {
+    let amm_instructions = transaction
+        .instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, instruction)| instruction.program_id == RAYDIUM_AMM_V4_PROGRAM_ID)
+        .collect::<Vec<_>>();
+
+    ray_logs(&transaction.log_messages)
+        .into_iter()
+        .filter_map(|(invocation, encoded)| {
+            let swap_log = decode_swap_log(encoded)
+                .inspect_err(|e| warn!("Malformed ray_log in '{}': {e}", transaction.signature))
+                .ok()??;
+            let (instruction_index, instruction) = amm_instructions.get(invocation)?;
+
+            trade(transaction, *instruction_index, instruction, swap_log, recorded_at)
+                .inspect_err(|e| warn!("Failed to attribute swap in '{}': {e}", transaction.signature))
+                .ok()
+        })
+        .collect()
+}
+
+/// `ray_log` events with the invocation of the AMM program that emitted them, invocations are counted in execution
+/// order, the same order instructions of a `TransactionUpdate` are in.
+fn ray_logs(log_messages: &[String]) -> Vec<(usize, &str)> {
+    let program_id = RAYDIUM_AMM_V4_PROGRAM_ID.to_string();
+    let (mut invocations, mut call_stack, mut ray_logs) = (0, vec![], vec![]);
+
+    for message in log_messages {
+        if let Some(encoded) = message.strip_prefix(RAY_LOG_PREFIX) {
+            if call_stack.last() == Some(&true) {
+                ray_logs.push((invocations - 1, encoded));
+            }
+            continue;
+        }
+
+        let Some((program, status)) = message.strip_prefix("Program ").and_then(|rest| rest.split_once(' ')) else {
+            continue;
+        };
+        if status.starts_with("invoke [") {
+            let amm_invocation = program == program_id;
+            invocations += usize::from(amm_invocation);
+            call_stack.push(amm_invocation);
+        } else if status == "success" || status.starts_with("failed") {
+            call_stack.pop();
+        }
+    }
+
+    ray_logs
+}
+
+/// Decodes a base64 `ray_log` event, `None` for events other than swaps.
+fn decode_swap_log(encoded: &str) -> Result<Option<SwapLog>> {
+    let data = STANDARD.decode(encoded).context("event is not base64")?;
+    let log_type = *data.first().context("event is empty")?;
+    if log_type != SWAP_BASE_IN_LOG && log_type != SWAP_BASE_OUT_LOG {
+        return Ok(None);
+    }
+
+    // SwapBaseIn: amount_in, minimum_out, direction, user_source, pool_coin, pool_pc, out_amount.
+    // SwapBaseOut: max_in, amount_out, direction, user_source, pool_coin, pool_pc, deduct_in.
+    let log = AccountLayout::new(&data, SWAP_LOG_LEN)?;
+    let direction = match log.u64(17)? {
+        COIN_TO_PC => TradeDirection::CoinToPc,
+        PC_TO_COIN => TradeDirection::PcToCoin,
+        direction => bail!("unknown swap direction {direction}"),
+    };
+    let (amount_in, amount_out) = match log_type {
+        SWAP_BASE_IN_LOG => (log.u64(1)?, log.u64(49)?),
+        _ => (log.u64(49)?, log.u64(9)?),
+    };
+
+    Ok(Some(SwapLog { log_type, direction, amount_in, amount_out }))
+}
+
+fn trade(
+    transaction: &TransactionUpdate,
+    instruction_index: usize,
+    instruction: &ExecutedInstruction,
+    swap_log: SwapLog,
+    recorded_at: NaiveDateTime,
+) -> Result<Trade> {
+    let expected_tag = match swap_log.log_type {
+        SWAP_BASE_IN_LOG => SWAP_BASE_IN_TAG,
+        _ => SWAP_BASE_OUT_TAG,
+    };
+    ensure!(
+        instruction.data.first() == Some(&expected_tag),
+        "instruction {instruction_index} is not the logged swap"
+    );
+    ensure!(
+        instruction.accounts.len() >= SWAP_MIN_ACCOUNTS,
+        "got {} swap accounts, expected at least {SWAP_MIN_ACCOUNTS}",
+        instruction.accounts.len()
+    );
+
+    Ok(Trade {
+        signature: transaction.signature,
+        instruction_index: u32::try_from(instruction_index)?,
+        pool_pubkey: instruction.accounts[POOL_ACCOUNT],
+        slot: transaction.slot,
+        direction: swap_log.direction,
+        amount_in: swap_log.amount_in,
+        amount_out: swap_log.amount_out,
+        user: instruction.accounts[instruction.accounts.len() - 1],
+        recorded_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signature;
+
+    fn ray_log(log_type: u8, fields: [u64; 7]) -> String {
+        let data = [
+            vec![log_type],
+            fields.iter().flat_map(|field| field.to_le_bytes()).collect(),
+        ]
+        .concat();
+        format!("{RAY_LOG_PREFIX}{}", STANDARD.encode(data))
+    }
+
+    fn instruction(program_id: Pubkey, tag: u8, accounts_len: usize) -> ExecutedInstruction {
+        let accounts = (0..accounts_len).map(|_| Pubkey::new_unique()).collect();
+        ExecutedInstruction { program_id, accounts, data: vec![tag] }
+    }
+
+    fn transaction(instructions: Vec<ExecutedInstruction>, log_messages: Vec<String>) -> TransactionUpdate {
+        TransactionUpdate {
+            signature: Signature::new_unique(),
+            slot: 42,
+            account_keys: vec![],
+            instructions,
+            log_messages,
+        }
+    }
+
+    #[test]
+    fn attributes_swaps_to_the_logging_instruction() {
+        let (amm, router, token) = (RAYDIUM_AMM_V4_PROGRAM_ID, Pubkey::new_unique(), spl_token::id());
+        let deposit = instruction(amm, 3, 14);
+        let swap = instruction(amm, SWAP_BASE_IN_TAG, 18);
+        let instructions = vec![
+            deposit,
+            instruction(router, 0, 0),
+            swap.clone(),
+            instruction(token, 3, 3),
+        ];
+        let log_messages = vec![
+            format!("Program {amm} invoke [1]"),
+            ray_log(1, [0; 7]),
+            format!("Program {amm} success"),
+            format!("Program {router} invoke [1]"),
+            format!("Program {amm} invoke [2]"),
+            ray_log(SWAP_BASE_IN_LOG, [1_000_000_000, 140_000_000, PC_TO_COIN, 5, 0, 0, 149_625_000]),
+            format!("Program {token} invoke [3]"),
+            format!("Program {token} success"),
+            format!("Program {amm} consumed 30000 of 200000 compute units"),
+            format!("Program {amm} success"),
+            format!("Program {router} success"),
+        ];
+        let transaction = transaction(instructions, log_messages);
+
+        let [trade] = trades(&transaction, Utc::now().naive_utc()).try_into().unwrap();
+        assert_eq!((trade.instruction_index, trade.pool_pubkey, trade.user), (2, swap.accounts[1], swap.accounts[17]));
+        assert_eq!(trade.direction, TradeDirection::PcToCoin);
+        assert_eq!((trade.amount_in, trade.amount_out), (1_000_000_000, 149_625_000));
+        assert_eq!((trade.signature, trade.slot), (transaction.signature, 42));
+    }
+
+    #[test]
+    fn takes_the_deducted_amount_of_exact_out_swaps() {
+        let swap = instruction(RAYDIUM_AMM_V4_PROGRAM_ID, SWAP_BASE_OUT_TAG, 17);
+        let log_messages = vec![
+            format!("Program {RAYDIUM_AMM_V4_PROGRAM_ID} invoke [1]"),
+            ray_log(SWAP_BASE_OUT_LOG, [2_000_000, 1_000_000_000, COIN_TO_PC, 5, 0, 0, 1_500_000]),
+            format!("Program {RAYDIUM_AMM_V4_PROGRAM_ID} success"),
+        ];
+
+        let [trade] = trades(&transaction(vec![swap], log_messages), Utc::now().naive_utc())
+            .try_into()
+            .unwrap();
+        assert_eq!(trade.direction, TradeDirection::CoinToPc);
+        assert_eq!((trade.amount_in, trade.amount_out), (1_500_000, 1_000_000_000));
+    }
+
+    #[test]
+    fn skips_logs_not_matching_the_instruction() {
+        let deposit = instruction(RAYDIUM_AMM_V4_PROGRAM_ID, 3, 17);
+        let log_messages = vec![
+            format!("Program {RAYDIUM_AMM_V4_PROGRAM_ID} invoke [1]"),
+            ray_log(SWAP_BASE_IN_LOG, [1, 1, COIN_TO_PC, 0, 0, 0, 1]),
+            format!("Program {RAYDIUM_AMM_V4_PROGRAM_ID} success"),
+        ];
+
+        assert!(trades(&transaction(vec![deposit], log_messages), Utc::now().naive_utc()).is_empty());
+    }
+}
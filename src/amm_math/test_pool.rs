@@ -0,0 +1,157 @@
+use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::rpc::in_memory::InMemoryAccountReceiver;
+use raydium_amm::processor::AUTHORITY_AMM;
+use raydium_amm::state::AmmInfo;
+use safe_transmute::to_bytes::transmute_one_to_bytes;
+use serum_dex::state::{
+    gen_vault_signer_key, AccountFlag, MarketState, OpenOrders, ToAlignedBytes, ACCOUNT_HEAD_PADDING,
+    ACCOUNT_TAIL_PADDING,
+};
+use solana_program::program_pack::Pack;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use spl_token::state::AccountState;
+use std::sync::Arc;
+
+/// `AmmStatus::Initialized`, the pool trades against its own orders on the market.
+pub const ORDERBOOK_STATUS: u64 = 1;
+/// `AmmStatus::SwapOnly`, the pool holds all of its funds in the vaults.
+pub const NO_ORDERBOOK_STATUS: u64 = 6;
+
+pub const COIN_DECIMALS: u64 = 9;
+pub const PC_DECIMALS: u64 = 6;
+pub const COIN_VAULT_AMOUNT: u64 = 1_000_000_000_000;
+pub const PC_VAULT_AMOUNT: u64 = 150_000_000_000;
+pub const OPEN_ORDERS_COIN_TOTAL: u64 = 250_000_000_000;
+pub const OPEN_ORDERS_PC_TOTAL: u64 = 40_000_000_000;
+pub const NEED_TAKE_PNL_COIN: u64 = 50_000_000_000;
+pub const NEED_TAKE_PNL_PC: u64 = 10_000_000_000;
+
+/// Events the empty event queue of the market has room for.
+const EVENT_QUEUE_CAPACITY: usize = 8;
+/// `size_of::<serum_dex::state::Event>()`.
+const EVENT_SIZE: usize = 88;
+
+/// A Raydium v4 pool and its OpenBook market, assembled from the program structs and served from memory.
+pub struct TestPool {
+    pub pool: Pubkey,
+    pub amm_info: AmmInfo,
+    pub market: Pubkey,
+    pub market_program: Pubkey,
+    pub vault_signer_nonce: u64,
+    pub event_q: Pubkey,
+    pub accounts: Arc<InMemoryAccountReceiver>,
+}
+
+impl TestPool {
+    pub fn new(status: u64) -> Self {
+        let (pool, market, market_program, event_q) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (amm_authority, nonce) = Pubkey::find_program_address(&[AUTHORITY_AMM], &RAYDIUM_AMM_V4_PROGRAM_ID);
+        let vault_signer_nonce = (0..)
+            .find(|nonce| gen_vault_signer_key(*nonce, &market, &market_program).is_ok())
+            .expect("no vault signer nonce");
+
+        let mut amm_info = AmmInfo {
+            status,
+            nonce: nonce as u64,
+            coin_decimals: COIN_DECIMALS,
+            pc_decimals: PC_DECIMALS,
+            coin_vault: Pubkey::new_unique(),
+            pc_vault: Pubkey::new_unique(),
+            coin_vault_mint: Pubkey::new_unique(),
+            pc_vault_mint: Pubkey::new_unique(),
+            lp_mint: Pubkey::new_unique(),
+            open_orders: Pubkey::new_unique(),
+            market,
+            market_program,
+            target_orders: Pubkey::new_unique(),
+            lp_amount: 1_000_000_000,
+            ..AmmInfo::default()
+        };
+        amm_info.fees.swap_fee_numerator = 25;
+        amm_info.fees.swap_fee_denominator = 10_000;
+        amm_info.state_data.need_take_pnl_coin = NEED_TAKE_PNL_COIN;
+        amm_info.state_data.need_take_pnl_pc = NEED_TAKE_PNL_PC;
+
+        let market_state = MarketState {
+            account_flags: (AccountFlag::Initialized | AccountFlag::Market).bits(),
+            own_address: market.to_aligned_bytes(),
+            vault_signer_nonce,
+            coin_mint: amm_info.coin_vault_mint.to_aligned_bytes(),
+            pc_mint: amm_info.pc_vault_mint.to_aligned_bytes(),
+            coin_vault: Pubkey::new_unique().to_aligned_bytes(),
+            coin_deposits_total: 0,
+            coin_fees_accrued: 0,
+            pc_vault: Pubkey::new_unique().to_aligned_bytes(),
+            pc_deposits_total: 0,
+            pc_fees_accrued: 0,
+            pc_dust_threshold: 0,
+            req_q: Pubkey::new_unique().to_aligned_bytes(),
+            event_q: event_q.to_aligned_bytes(),
+            bids: Pubkey::new_unique().to_aligned_bytes(),
+            asks: Pubkey::new_unique().to_aligned_bytes(),
+            coin_lot_size: 1_000_000,
+            pc_lot_size: 100,
+            fee_rate_bps: 0,
+            referrer_rebates_accrued: 0,
+        };
+        let open_orders = OpenOrders {
+            account_flags: (AccountFlag::Initialized | AccountFlag::OpenOrders).bits(),
+            market: market.to_aligned_bytes(),
+            owner: amm_authority.to_aligned_bytes(),
+            native_coin_free: 0,
+            native_coin_total: OPEN_ORDERS_COIN_TOTAL,
+            native_pc_free: 0,
+            native_pc_total: OPEN_ORDERS_PC_TOTAL,
+            free_slot_bits: u128::MAX,
+            is_bid_bits: 0,
+            orders: [0; 128],
+            client_order_ids: [0; 128],
+            referrer_rebates_accrued: 0,
+        };
+        // Header of an empty queue: flags, head, count and sequence number, followed by room for the events.
+        let mut event_queue = [(AccountFlag::Initialized | AccountFlag::EventQueue).bits(), 0, 0, 0]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        event_queue.resize(event_queue.len() + EVENT_QUEUE_CAPACITY * EVENT_SIZE, 0);
+
+        let accounts = InMemoryAccountReceiver::default();
+        accounts.insert(amm_info.coin_vault, token_account(amm_info.coin_vault_mint, amm_authority, COIN_VAULT_AMOUNT));
+        accounts.insert(amm_info.pc_vault, token_account(amm_info.pc_vault_mint, amm_authority, PC_VAULT_AMOUNT));
+        accounts.insert(market, dex_account(market_program, transmute_one_to_bytes(&market_state)));
+        accounts.insert(amm_info.open_orders, dex_account(market_program, bytemuck::bytes_of(&open_orders)));
+        accounts.insert(event_q, dex_account(market_program, &event_queue));
+
+        Self {
+            pool,
+            amm_info,
+            market,
+            market_program,
+            vault_signer_nonce,
+            event_q,
+            accounts: Arc::new(accounts),
+        }
+    }
+
+    pub fn amm_info_data(&self) -> Vec<u8> {
+        transmute_one_to_bytes(&self.amm_info).to_vec()
+    }
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    let token_account =
+        spl_token::state::Account { mint, owner, amount, state: AccountState::Initialized, ..Default::default() };
+    token_account.pack_into_slice(&mut data);
+
+    Account { lamports: 2_039_280, data, owner: spl_token::id(), ..Account::default() }
+}
+
+/// Wraps the state of a dex account into its `serum` and `padding` markers.
+fn dex_account(market_program: Pubkey, state: &[u8]) -> Account {
+    let data = [&ACCOUNT_HEAD_PADDING[..], state, &ACCOUNT_TAIL_PADDING[..]].concat();
+
+    Account { lamports: 1_000_000_000, data, owner: market_program, ..Account::default() }
+}
@@ -0,0 +1,94 @@
+use crate::amm_math::account_layout::AccountLayout;
+use crate::models::Price;
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::BigDecimal;
+use solana_sdk::pubkey::Pubkey;
+
+const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+const WHIRLPOOL_LEN: usize = 653;
+
+const TICK_SPACING_OFFSET: usize = 41;
+const FEE_RATE_OFFSET: usize = 45;
+const LIQUIDITY_OFFSET: usize = 49;
+const SQRT_PRICE_OFFSET: usize = 65;
+const TICK_CURRENT_INDEX_OFFSET: usize = 81;
+const TOKEN_MINT_A_OFFSET: usize = 101;
+const TOKEN_VAULT_A_OFFSET: usize = 133;
+const TOKEN_MINT_B_OFFSET: usize = 181;
+const TOKEN_VAULT_B_OFFSET: usize = 213;
+
+/// `fee_rate` is stored in hundredths of a basis point, i.e. millionths.
+const FEE_RATE_SCALE: i64 = 6;
+
+/// Fields of the Orca `Whirlpool` account needed for pricing.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhirlpoolState {
+    pub tick_spacing: u16,
+    pub fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+}
+
+impl WhirlpoolState {
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let layout = AccountLayout::anchor(data, WHIRLPOOL_DISCRIMINATOR, WHIRLPOOL_LEN)?;
+
+        Ok(Self {
+            tick_spacing: layout.u16(TICK_SPACING_OFFSET)?,
+            fee_rate: layout.u16(FEE_RATE_OFFSET)?,
+            liquidity: layout.u128(LIQUIDITY_OFFSET)?,
+            sqrt_price: layout.u128(SQRT_PRICE_OFFSET)?,
+            tick_current_index: layout.i32(TICK_CURRENT_INDEX_OFFSET)?,
+            token_mint_a: layout.pubkey(TOKEN_MINT_A_OFFSET)?,
+            token_vault_a: layout.pubkey(TOKEN_VAULT_A_OFFSET)?,
+            token_mint_b: layout.pubkey(TOKEN_MINT_B_OFFSET)?,
+            token_vault_b: layout.pubkey(TOKEN_VAULT_B_OFFSET)?,
+        })
+    }
+
+    /// Swap fee as a fraction of the input amount.
+    pub fn fee(&self) -> BigDecimal {
+        BigDecimal::new(BigInt::from(self.fee_rate), FEE_RATE_SCALE)
+    }
+}
+
+/// Price of token A in token B, UI units. Whirlpools do not store mint decimals, so they come from the mints.
+pub fn calc_whirlpool_price(pool: &WhirlpoolState, decimals_a: u8, decimals_b: u8) -> Price {
+    Price::from_sqrt_price_x64(pool.sqrt_price, decimals_a, decimals_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn encoded_whirlpool(sqrt_price: u128, fee_rate: u16) -> Vec<u8> {
+        let mut data = vec![0u8; WHIRLPOOL_LEN];
+        data[..8].copy_from_slice(&WHIRLPOOL_DISCRIMINATOR);
+        data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2].copy_from_slice(&64u16.to_le_bytes());
+        data[FEE_RATE_OFFSET..FEE_RATE_OFFSET + 2].copy_from_slice(&fee_rate.to_le_bytes());
+        data[SQRT_PRICE_OFFSET..SQRT_PRICE_OFFSET + 16].copy_from_slice(&sqrt_price.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_whirlpool() {
+        let pool = WhirlpoolState::decode(&encoded_whirlpool(1u128 << 63, 3_000)).unwrap();
+
+        assert_eq!(pool.tick_spacing, 64);
+        assert_eq!(pool.fee(), BigDecimal::from_str("0.003").unwrap());
+        // Raw price of 0.25 with 9/6 decimals gives 0.25 * 10^3 in UI units.
+        assert_eq!(calc_whirlpool_price(&pool, 9, 6), Price::from_str("250").unwrap());
+    }
+
+    #[test]
+    fn rejects_truncated_account() {
+        assert!(WhirlpoolState::decode(&encoded_whirlpool(1u128 << 64, 0)[..100]).is_err());
+    }
+}
@@ -1,11 +1,19 @@
+use crate::amm_math::quote::{SwapAmount, SwapDirection};
+use crate::models::NewPool;
 use crate::price_fetcher::{PriceFetchResponseType, PriceFetchService};
+use anyhow::Context;
 use solana_program::pubkey::Pubkey;
 use std::str::FromStr;
 use std::sync::Arc;
 use teloxide::prelude::{Message, Requester};
-use teloxide::Bot;
 use teloxide::repls::CommandReplExt;
+use teloxide::types::ChatId;
 use teloxide::utils::command::BotCommands;
+use teloxide::Bot;
+use tokio::spawn;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tracing::{error, warn};
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -14,25 +22,50 @@ enum Command {
     Average,
     #[command(description = "Last actual price.")]
     Current,
+    #[command(description = "OpenBook best bid, best ask, mid and spread next to the AMM price, '/book <pool>'.")]
+    Book(String),
+    #[command(description = "State of the account stream of the pool.")]
+    Status,
+    #[command(
+        description = "Swap quote, e.g. '/quote coin in 1000000' sells exactly 1000000 of the smallest coin units.",
+        parse_with = "split"
+    )]
+    Quote { sold: String, exact: String, amount: u64 },
 }
 
-
-pub async fn setup_bot(price_fetch_service: Arc<PriceFetchService>) -> anyhow::Result<()> {
+pub async fn setup_bot(price_fetch_service: Arc<PriceFetchService>, announce_chat_ids: Vec<i64>) -> anyhow::Result<()> {
     let bot = Bot::from_env();
 
-    bot.set_my_commands(Command::bot_commands()).await.expect("Failed to set commands.");
+    bot.set_my_commands(Command::bot_commands())
+        .await
+        .context("Failed to set commands")?;
+
+    if let Some(new_pools) = price_fetch_service
+        .new_pools()
+        .filter(|_| !announce_chat_ids.is_empty())
+    {
+        spawn(announce_new_pools(bot.clone(), new_pools, announce_chat_ids.into_iter().map(ChatId).collect()));
+    }
 
     // TODO: Provide multiple pools support
-    let pool_address = Pubkey::from_str("EP2ib6dYdEeqD8MfE2ezHCxX3kP3K2eLKkirfPm5eyMx").unwrap();
+    let default_pool = price_fetch_service.default_pool();
 
     Command::repl(bot, move |bot: Bot, msg: Message, cmd: Command| {
         let price_fetch_service = price_fetch_service.clone();
         async move {
+            let pool_address = match command_pool(&cmd, default_pool) {
+                Ok(pool_address) => pool_address,
+                Err(response) => {
+                    bot.send_message(msg.chat.id, response).await?;
+                    return Ok(());
+                }
+            };
+
             match cmd {
                 Command::Average => {
                     let average_resp = price_fetch_service.average(&pool_address).await;
 
-                    if let PriceFetchResponseType::NoDataFound  = average_resp.response_type {
+                    if let PriceFetchResponseType::NoDataFound = average_resp.response_type {
                         let current_resp = price_fetch_service.current(&pool_address).await;
                         let response = format!("No data found for last 5 minutes. {current_resp}");
                         bot.send_message(msg.chat.id, response).await?;
@@ -44,11 +77,78 @@ pub async fn setup_bot(price_fetch_service: Arc<PriceFetchService>) -> anyhow::R
                     let current_resp = price_fetch_service.current(&pool_address).await;
                     bot.send_message(msg.chat.id, format!("{current_resp}")).await?;
                 }
+                Command::Book(_) => {
+                    let book_resp = price_fetch_service.order_book(&pool_address).await;
+                    bot.send_message(msg.chat.id, format!("{book_resp}")).await?;
+                }
+                Command::Quote { sold, exact, amount } => {
+                    let response = match parse_swap(&sold, &exact, amount) {
+                        Some((direction, amount)) => price_fetch_service
+                            .quote(&pool_address, direction, amount)
+                            .await
+                            .to_string(),
+                        None => "Usage: /quote <coin|pc> <in|out> <amount>.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, response).await?;
+                }
+                Command::Status => {
+                    let status_resp = price_fetch_service.connection_status(&pool_address).await;
+                    bot.send_message(msg.chat.id, format!("{status_resp}")).await?;
+                }
             }
 
-            return Ok(())
+            return Ok(());
         }
-    }).await;
+    })
+    .await;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Pool given to the command, the first listed pool otherwise. The reply to send back when there is none.
+fn command_pool(cmd: &Command, default_pool: Option<Pubkey>) -> Result<Pubkey, String> {
+    match cmd {
+        Command::Book(pool) if !pool.trim().is_empty() => {
+            let pool = pool.trim();
+            Pubkey::from_str(pool).map_err(|e| format!("Invalid pool address '{pool}': {e}."))
+        }
+        _ => default_pool.ok_or_else(|| "No pool configured.".to_string()),
+    }
+}
+
+/// Direction from the sold token and the side the amount is exact on.
+fn parse_swap(sold: &str, exact: &str, amount: u64) -> Option<(SwapDirection, SwapAmount)> {
+    let direction = match sold {
+        "coin" => SwapDirection::CoinToPc,
+        "pc" => SwapDirection::PcToCoin,
+        _ => return None,
+    };
+    let amount = match exact {
+        "in" => SwapAmount::ExactIn(amount),
+        "out" => SwapAmount::ExactOut(amount),
+        _ => return None,
+    };
+
+    Some((direction, amount))
+}
+
+/// Sends every detected pool to the chats, pools detected while the announcer lags behind are skipped.
+async fn announce_new_pools(bot: Bot, mut new_pools: Receiver<NewPool>, chat_ids: Vec<ChatId>) {
+    loop {
+        let new_pool = match new_pools.recv().await {
+            Ok(new_pool) => new_pool,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Skipped announcing {skipped} new pools");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        for chat_id in &chat_ids {
+            bot.send_message(*chat_id, new_pool.to_string())
+                .await
+                .inspect_err(|e| error!("Failed to announce pool '{}' to chat {chat_id}: {e}", new_pool.pool_pubkey))
+                .ok();
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use bigdecimal::BigDecimal;
 use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use solana_sdk::pubkey::Pubkey;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 const DEFAULT_CONFIG_FILE_PREFIX: &str = "config";
 const DEFAULT_CONFIG_FILE_NAME: &str = "default.toml";
@@ -10,6 +13,14 @@ pub struct Settings {
     pub database: Database,
     pub liquidity_pool: LiquidityPool,
     pub rpc: Rpc,
+    #[serde(default)]
+    pub recorder: Recorder,
+    #[serde(default)]
+    pub all_pools: AllPools,
+    #[serde(default)]
+    pub new_pools: NewPools,
+    #[serde(default)]
+    pub trades: Trades,
 }
 
 #[derive(Deserialize, Clone)]
@@ -17,6 +28,53 @@ pub struct LiquidityPool {
     pub account_addresses_base54: Vec<String>,
 }
 
+/// Records stream updates and JSON-RPC responses to a JSONL capture file that can be replayed later.
+#[derive(Deserialize, Clone, Default)]
+pub struct Recorder {
+    pub capture_path: Option<String>,
+    /// Replays the capture file through the pricing pipeline instead of streaming, the bot is not started then.
+    /// Replayed prices are logged and kept in memory, the database is not touched.
+    pub replay_path: Option<String>,
+}
+
+/// Streams every Raydium AMM v4 pool and keeps their prices in memory, requires the Yellowstone backend.
+///
+/// Only pools meeting all of the criteria are persisted.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AllPools {
+    pub enabled: bool,
+    /// Pools with one of these mints on either side, any mint when empty.
+    #[serde(deserialize_with = "deserialize_pubkeys")]
+    pub mint_allowlist: Vec<Pubkey>,
+    /// Pools holding at least this much of their quote token, in UI units.
+    pub min_quote_liquidity: Option<BigDecimal>,
+    /// Pool updates priced per second at most, each one reads the vaults and market of the pool through JSON-RPC.
+    /// Unlimited when missing.
+    pub max_pricings_per_second: Option<u32>,
+}
+
+/// Watches the transactions of the Raydium AMM v4 program for created pools, requires the Yellowstone backend.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct NewPools {
+    pub enabled: bool,
+    /// Pools with one of these mints on either side, any mint when empty.
+    #[serde(deserialize_with = "deserialize_pubkeys")]
+    pub mint_allowlist: Vec<Pubkey>,
+    /// Prices and persists the detected pools like the listed ones.
+    pub auto_track: bool,
+    /// Telegram chats every detected pool is announced to.
+    pub announce_chat_ids: Vec<i64>,
+}
+
+/// Persists the swaps executed against the tracked Raydium AMM v4 pools, requires the Yellowstone backend.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Trades {
+    pub enabled: bool,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Database {
     pub connection_url: String,
@@ -28,8 +86,54 @@ pub struct Database {
 
 #[derive(Deserialize, Clone)]
 pub struct Rpc {
-    pub yellowstone_grpc_endpoint: String,
+    pub stream_backend: StreamBackend,
+    /// Solana PubSub endpoint, required by the `websocket` backend.
+    pub websocket_endpoint: Option<String>,
+    /// Endpoints in priority order.
+    pub yellowstone_grpc_endpoints: Vec<GrpcEndpoint>,
+    pub yellowstone_grpc_mode: GrpcEndpointMode,
+    pub yellowstone_grpc_connect_timeout_secs: Option<u64>,
+    pub yellowstone_grpc_request_timeout_secs: Option<u64>,
+    /// A stream that doesn't answer a ping with a pong until the next ping is reconnected.
+    pub yellowstone_grpc_ping_interval_secs: u64,
+    /// Number of gRPC streams the tracked accounts are spread over.
+    pub yellowstone_grpc_streams: usize,
     pub json_rpc_endpoint: String,
+    /// Used in turn with `json_rpc_endpoint`, a failed request is retried on the next one.
+    pub json_rpc_additional_endpoints: Vec<String>,
+    /// Budget shared by all JSON-RPC endpoints, unlimited when missing.
+    pub json_rpc_requests_per_second: Option<u32>,
+    pub json_rpc_max_retries: u32,
+    /// Accounts of a stream that stays disconnected this long are polled through JSON-RPC until it is back.
+    pub json_rpc_polling_fallback_after_secs: u64,
+    pub json_rpc_polling_interval_ms: u64,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct GrpcEndpoint {
+    pub url: String,
+    pub x_token: Option<String>,
+    /// PEM file of the CA that signed the endpoint certificate, the system roots are used without it.
+    pub tls_ca_certificate_path: Option<String>,
+    pub tls_domain_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamBackend {
+    YellowstoneGrpc,
+    /// `accountSubscribe` over WebSocket, for setups without a Yellowstone endpoint.
+    #[serde(rename = "websocket")]
+    WebSocket,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GrpcEndpointMode {
+    /// One connection per stream, the next endpoint is used when the current one fails.
+    Failover,
+    /// Every endpoint streams the same accounts, the first arrival of an update wins.
+    Race,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq)]
@@ -70,6 +174,15 @@ impl Settings {
     }
 }
 
+fn deserialize_pubkeys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Pubkey>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pubkey| {
+            Pubkey::from_str(pubkey).map_err(|e| serde::de::Error::custom(format!("invalid pubkey '{pubkey}': {e}")))
+        })
+        .collect()
+}
+
 fn default_config_file_path(base_path: &str) -> PathBuf {
     find_config_file(base_path, DEFAULT_CONFIG_FILE_NAME)
 }
@@ -1,19 +1,23 @@
+use crate::bot::setup_bot;
 use crate::config::Settings;
 use crate::price_fetcher::PriceFetchService;
-use crate::storage::PostgresStorage;
-use solana_client::nonblocking::rpc_client::RpcClient;
+use crate::rpc::capture::Capture;
+use crate::rpc::resilient::ResilientJsonRpcAccountReceiver;
+use crate::storage::{MemoryStorage, PostgresStorage};
+use std::future::pending;
 use std::io;
 use std::sync::Arc;
+use tracing::{error, info};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 use trait_ext::arced_ext::Arced;
-use crate::bot::setup_bot;
 
 mod amm_math;
 mod bot;
 mod config;
 mod models;
+mod pool_pricer;
 mod price_fetcher;
 mod rpc;
 mod storage;
@@ -22,15 +26,31 @@ mod trait_ext;
 #[tokio::main]
 async fn main() -> io::Result<()> {
     dotenv::dotenv().expect("Failed to read '.env' file");
-    let settings =
-        Settings::load(None, None).unwrap_or_else(|e| panic!("Configuration failed: '{e}'!"));
+    let settings = Settings::load(None, None).unwrap_or_else(|e| panic!("Configuration failed: '{e}'!"));
     set_up_logging("info");
 
+    if let Some(replay_path) = &settings.recorder.replay_path {
+        let capture = Capture::load(replay_path).await.expect("Can't load capture");
+        let storage = MemoryStorage::default().arced();
+        PriceFetchService::replay(settings.clone(), storage.clone(), storage.clone(), &capture)
+            .await
+            .expect("Can't replay capture");
+
+        for price in storage.prices().await {
+            let (pool, slot) = (price.pool_pubkey, price.slot);
+            info!("Replayed price {price} of '{pool}' at slot {slot}", price = price.price);
+        }
+        return Ok(());
+    }
+
     let storage = PostgresStorage::from_settings(&settings)
         .await
         .expect("Can't create storage")
         .arced();
-    let json_rpc = RpcClient::new(settings.rpc.json_rpc_endpoint.clone()).arced();
+
+    let json_rpc = ResilientJsonRpcAccountReceiver::from_config(&settings.rpc)
+        .expect("Can't create JSON-RPC client")
+        .arced();
 
     let price_fetcher = PriceFetchService::from_settings(&settings, storage.clone(), storage, json_rpc)
         .await
@@ -40,8 +60,10 @@ async fn main() -> io::Result<()> {
         .start_price_fetching_in_background()
         .expect("Can't start price fetch");
 
-    setup_bot(price_fetcher).await.ok();
-
+    if let Err(e) = setup_bot(price_fetcher, settings.new_pools.announce_chat_ids.clone()).await {
+        error!("Telegram bot stopped, prices are still fetched: {e:?}");
+        pending::<()>().await;
+    }
 
     Ok(())
 }
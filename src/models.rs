@@ -1,16 +1,109 @@
+pub mod price;
+
 use chrono::NaiveDateTime;
+pub use price::Price;
+use solana_sdk::clock::Slot;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 use sqlx::FromRow;
+use std::fmt::Display;
 
 #[derive(Clone, Debug, Default, PartialEq, FromRow)]
 pub struct PoolAndPrice {
     pub pool_pubkey: Pubkey,
-    pub price: f64,
+    pub price: Price,
+    /// Slot of the on-chain state the price was computed from.
+    pub slot: Slot,
     pub updated_at: NaiveDateTime,
 }
 
 impl PoolAndPrice {
-    pub fn new(pool_pubkey: Pubkey, price: f64, updated_at: NaiveDateTime) -> Self {
-        Self { pool_pubkey, price, updated_at }
+    pub fn new(pool_pubkey: Pubkey, price: Price, slot: Slot, updated_at: NaiveDateTime) -> Self {
+        Self { pool_pubkey, price, slot, updated_at }
+    }
+}
+
+/// Slots during which updates of the pool may have been missed, prices inside the range are not recorded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PriceGap {
+    pub pool_pubkey: Pubkey,
+    /// Last slot seen before the stream was lost, `None` when nothing was received before.
+    pub from_slot: Option<Slot>,
+    /// Slot of the snapshot the pool was resynchronized from.
+    pub to_slot: Slot,
+    pub detected_at: NaiveDateTime,
+}
+
+impl PriceGap {
+    pub fn new(pool_pubkey: Pubkey, from_slot: Option<Slot>, to_slot: Slot, detected_at: NaiveDateTime) -> Self {
+        Self { pool_pubkey, from_slot, to_slot, detected_at }
+    }
+}
+
+/// Raydium AMM v4 pool created by an `Initialize2` instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewPool {
+    pub pool_pubkey: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    /// Unix timestamp trading opens at.
+    pub open_time: u64,
+    pub init_pc_amount: u64,
+    pub init_coin_amount: u64,
+    pub signature: Signature,
+    pub slot: Slot,
+}
+
+impl Display for NewPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let opens_at = NaiveDateTime::from_timestamp_opt(self.open_time as i64, 0)
+            .map_or_else(|| self.open_time.to_string(), |opens_at| format!("{opens_at} UTC"));
+
+        write!(
+            f,
+            "New Raydium pool {pool} for {coin_mint}/{pc_mint} on market {market}, \
+             created by {creator} at slot {slot}. Trading opens at {opens_at}.",
+            pool = self.pool_pubkey,
+            coin_mint = self.coin_mint,
+            pc_mint = self.pc_mint,
+            market = self.market,
+            creator = self.creator,
+            slot = self.slot,
+        )
     }
 }
+
+/// Side of the pool the trader pays into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeDirection {
+    CoinToPc,
+    PcToCoin,
+}
+
+impl TradeDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeDirection::CoinToPc => "coin_to_pc",
+            TradeDirection::PcToCoin => "pc_to_coin",
+        }
+    }
+}
+
+/// Swap executed against a pool, amounts are in base units of the tokens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trade {
+    pub signature: Signature,
+    /// Position of the swap among the instructions of the transaction, inner instructions included.
+    pub instruction_index: u32,
+    pub pool_pubkey: Pubkey,
+    pub slot: Slot,
+    pub direction: TradeDirection,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Owner of the token account the trader paid from.
+    pub user: Pubkey,
+    pub recorded_at: NaiveDateTime,
+}
@@ -0,0 +1,165 @@
+use anyhow::bail;
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, One, ParseBigDecimalError};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::ops::Sub;
+use std::str::FromStr;
+
+/// Significant digits kept when rounding inexact intermediate results, e.g. bin price powers.
+pub(crate) const CALCULATION_PRECISION: u64 = 64;
+/// Significant digits shown to users.
+const DISPLAY_PRECISION: u64 = 12;
+
+/// Exact decimal price of the base token in the quote token, UI units.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct Price(BigDecimal);
+
+impl Price {
+    pub fn new(value: BigDecimal) -> Self {
+        Self(value)
+    }
+
+    /// Price from raw token amounts, e.g. pool reserves or the legs of a swap.
+    pub fn from_amounts(
+        quote_amount: u64,
+        quote_decimals: u64,
+        base_amount: u64,
+        base_decimals: u64,
+    ) -> anyhow::Result<Self> {
+        if base_amount == 0 {
+            bail!("base amount is zero");
+        }
+
+        let quote = BigDecimal::new(BigInt::from(quote_amount), quote_decimals as i64);
+        let base = BigDecimal::new(BigInt::from(base_amount), base_decimals as i64);
+        Ok(Self(quote / base))
+    }
+
+    /// Price from a Q64.64 square root price of token 0 in token 1, as used by concentrated liquidity pools.
+    pub fn from_sqrt_price_x64(sqrt_price_x64: u128, decimals_0: u8, decimals_1: u8) -> Self {
+        let sqrt_price = BigInt::from(sqrt_price_x64);
+        let raw_price = BigDecimal::from(&sqrt_price * &sqrt_price) / BigDecimal::from(BigInt::one() << 128u32);
+
+        Self(raw_price * decimals_scale(decimals_0, decimals_1))
+    }
+
+    /// Price of a liquidity bin, `(1 + bin_step / 10_000) ^ bin_id` in the smallest units of both tokens.
+    pub fn from_bin_id(bin_id: i32, bin_step: u16, decimals_x: u8, decimals_y: u8) -> Self {
+        let factor = BigDecimal::new(BigInt::from(10_000 + u32::from(bin_step)), 4);
+
+        Self(powi(&factor, bin_id) * decimals_scale(decimals_x, decimals_y))
+    }
+
+    pub fn midpoint(&self, other: &Price) -> Price {
+        Self((&self.0 + &other.0).half())
+    }
+}
+
+/// `base ^ exponent` by squaring, intermediate results are rounded to the calculation precision.
+pub(crate) fn powi(base: &BigDecimal, exponent: i32) -> BigDecimal {
+    let (mut factor, mut result) = (base.clone(), BigDecimal::one());
+    let mut remaining = exponent.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = (result * &factor).with_prec(CALCULATION_PRECISION);
+        }
+        factor = factor.square().with_prec(CALCULATION_PRECISION);
+        remaining >>= 1;
+    }
+
+    if exponent < 0 {
+        (BigDecimal::one() / result).with_prec(CALCULATION_PRECISION)
+    } else {
+        result
+    }
+}
+
+/// `10 ^ (base_decimals - quote_decimals)`, converts a raw price into UI units.
+fn decimals_scale(base_decimals: u8, quote_decimals: u8) -> BigDecimal {
+    BigDecimal::new(BigInt::one(), i64::from(quote_decimals) - i64::from(base_decimals))
+}
+
+impl Sub for &Price {
+    type Output = Price;
+
+    fn sub(self, other: &Price) -> Price {
+        Price(&self.0 - &other.0)
+    }
+}
+
+impl From<BigDecimal> for Price {
+    fn from(value: BigDecimal) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Price> for BigDecimal {
+    fn from(price: Price) -> Self {
+        price.0
+    }
+}
+
+impl FromStr for Price {
+    type Err = ParseBigDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BigDecimal::from_str(s).map(Self)
+    }
+}
+
+impl Display for Price {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let rounded = if self.0.digits() > DISPLAY_PRECISION {
+            self.0.with_prec(DISPLAY_PRECISION)
+        } else {
+            self.0.clone()
+        };
+
+        write!(f, "{}", rounded.normalized())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(value: &str) -> Price {
+        Price::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn keeps_tiny_prices_exact() {
+        // 1 quote unit (6 decimals) for 10^15 base units (6 decimals) of a meme token with a huge supply.
+        let tiny = Price::from_amounts(1, 6, 1_000_000_000_000_000, 6).unwrap();
+
+        assert_eq!(tiny, price("0.000000000000001"));
+        assert_eq!(tiny.to_string(), "0.000000000000001");
+    }
+
+    #[test]
+    fn adjusts_amounts_by_decimals() {
+        assert_eq!(Price::from_amounts(50_000_000_000, 6, 1_000_000_000, 9).unwrap(), price("50000"));
+        assert!(Price::from_amounts(1, 6, 0, 9).is_err());
+    }
+
+    #[test]
+    fn converts_sqrt_price() {
+        assert_eq!(Price::from_sqrt_price_x64(2u128 << 64, 9, 6), price("4000"));
+        assert_eq!(Price::from_sqrt_price_x64(1u128 << 63, 6, 6), price("0.25"));
+    }
+
+    #[test]
+    fn converts_bin_id() {
+        assert_eq!(Price::from_bin_id(0, 25, 9, 6), price("1000"));
+        assert_eq!(Price::from_bin_id(2, 100, 6, 6), price("1.0201"));
+        assert_eq!(Price::from_bin_id(-1, 2_500, 6, 6), price("0.8"));
+    }
+
+    #[test]
+    fn rounds_for_display() {
+        assert_eq!(price("1.23456789012345").to_string(), "1.23456789012");
+        assert_eq!(price("1000").to_string(), "1000");
+    }
+}
@@ -0,0 +1,59 @@
+pub mod meteora_dlmm;
+pub mod orca_whirlpool;
+pub mod raydium_clmm;
+pub mod raydium_cpmm;
+pub mod raydium_v4;
+
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use crate::models::Price;
+use crate::pool_pricer::meteora_dlmm::MeteoraDlmmPricer;
+use crate::pool_pricer::orca_whirlpool::OrcaWhirlpoolPricer;
+use crate::pool_pricer::raydium_clmm::RaydiumClmmPricer;
+use crate::pool_pricer::raydium_cpmm::RaydiumCpmmPricer;
+use crate::pool_pricer::raydium_v4::RaydiumV4Pricer;
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Prices pools of a single DEX program from the raw data of the pool account.
+#[async_trait]
+pub trait PoolPricer: Send + Sync {
+    /// Program owning the pool accounts this pricer understands.
+    fn program_id(&self) -> Pubkey;
+
+    /// Accounts besides the pool itself whose state is needed to price the pool.
+    async fn dependent_accounts(&self, pool_pubkey: &Pubkey, pool_account: &Account) -> anyhow::Result<Vec<Pubkey>>;
+
+    /// Price of the base token in the quote token, UI units.
+    ///
+    /// `slot` is the slot of `pool_account`, other accounts are never read at an older state.
+    async fn price(&self, pool_pubkey: &Pubkey, pool_account: &Account, slot: Slot) -> anyhow::Result<Price>;
+}
+
+#[derive(Clone, Default)]
+pub struct PoolPricerRegistry {
+    pricers: HashMap<Pubkey, Arc<dyn PoolPricer>>,
+}
+
+impl PoolPricerRegistry {
+    pub fn with_default_pricers(client: Arc<JsonRpcAccountReceiverClient>) -> Self {
+        let mut registry = Self::default();
+        registry.register(Arc::new(RaydiumV4Pricer::new(client.clone())));
+        registry.register(Arc::new(RaydiumClmmPricer));
+        registry.register(Arc::new(RaydiumCpmmPricer::new(client.clone())));
+        registry.register(Arc::new(OrcaWhirlpoolPricer::new(client.clone())));
+        registry.register(Arc::new(MeteoraDlmmPricer::new(client)));
+        registry
+    }
+
+    pub fn register(&mut self, pricer: Arc<dyn PoolPricer>) {
+        self.pricers.insert(pricer.program_id(), pricer);
+    }
+
+    pub fn get(&self, program_id: &Pubkey) -> Option<Arc<dyn PoolPricer>> {
+        self.pricers.get(program_id).cloned()
+    }
+}
@@ -0,0 +1,50 @@
+use crate::amm_math::dlmm::{calc_active_bin_price, LbPairState};
+use crate::amm_math::mint::MintDecimalsCache;
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use crate::models::Price;
+use crate::pool_pricer::PoolPricer;
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use tracing::debug;
+
+pub const METEORA_DLMM_PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+
+pub struct MeteoraDlmmPricer {
+    client: Arc<JsonRpcAccountReceiverClient>,
+    mint_decimals: MintDecimalsCache,
+}
+
+impl MeteoraDlmmPricer {
+    pub fn new(client: Arc<JsonRpcAccountReceiverClient>) -> Self {
+        Self { client, mint_decimals: MintDecimalsCache::default() }
+    }
+}
+
+#[async_trait]
+impl PoolPricer for MeteoraDlmmPricer {
+    fn program_id(&self) -> Pubkey {
+        METEORA_DLMM_PROGRAM_ID
+    }
+
+    /// The price lives in the `LbPair` account, the mints are only read for their decimals, which never change.
+    async fn dependent_accounts(&self, _pool_pubkey: &Pubkey, _pool_account: &Account) -> anyhow::Result<Vec<Pubkey>> {
+        Ok(vec![])
+    }
+
+    async fn price(&self, pool_pubkey: &Pubkey, pool_account: &Account, slot: Slot) -> anyhow::Result<Price> {
+        let lb_pair = LbPairState::decode(&pool_account.data)?;
+        debug!(
+            "DLMM pair '{pool_pubkey}' active bin {active_id}, bin step {bin_step}",
+            active_id = lb_pair.active_id,
+            bin_step = lb_pair.bin_step,
+        );
+        let mints = [lb_pair.token_x_mint, lb_pair.token_y_mint];
+        let decimals = self.mint_decimals.get(self.client.clone(), &mints, Some(slot)).await?;
+
+        Ok(calc_active_bin_price(&lb_pair, decimals[0], decimals[1]))
+    }
+}
@@ -0,0 +1,51 @@
+use crate::amm_math::mint::MintDecimalsCache;
+use crate::amm_math::whirlpool::{calc_whirlpool_price, WhirlpoolState};
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use crate::models::Price;
+use crate::pool_pricer::PoolPricer;
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use tracing::debug;
+
+pub const ORCA_WHIRLPOOL_PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+
+pub struct OrcaWhirlpoolPricer {
+    client: Arc<JsonRpcAccountReceiverClient>,
+    mint_decimals: MintDecimalsCache,
+}
+
+impl OrcaWhirlpoolPricer {
+    pub fn new(client: Arc<JsonRpcAccountReceiverClient>) -> Self {
+        Self { client, mint_decimals: MintDecimalsCache::default() }
+    }
+}
+
+#[async_trait]
+impl PoolPricer for OrcaWhirlpoolPricer {
+    fn program_id(&self) -> Pubkey {
+        ORCA_WHIRLPOOL_PROGRAM_ID
+    }
+
+    /// The price lives in the `Whirlpool` account, the mints are only read for their decimals, which never change.
+    async fn dependent_accounts(&self, _pool_pubkey: &Pubkey, _pool_account: &Account) -> anyhow::Result<Vec<Pubkey>> {
+        Ok(vec![])
+    }
+
+    async fn price(&self, pool_pubkey: &Pubkey, pool_account: &Account, slot: Slot) -> anyhow::Result<Price> {
+        let pool = WhirlpoolState::decode(&pool_account.data)?;
+        debug!(
+            "Whirlpool '{pool_pubkey}' tick spacing {tick_spacing}, fee {fee}, liquidity {liquidity}",
+            tick_spacing = pool.tick_spacing,
+            fee = pool.fee(),
+            liquidity = pool.liquidity,
+        );
+        let mints = [pool.token_mint_a, pool.token_mint_b];
+        let decimals = self.mint_decimals.get(self.client.clone(), &mints, Some(slot)).await?;
+
+        Ok(calc_whirlpool_price(&pool, decimals[0], decimals[1]))
+    }
+}
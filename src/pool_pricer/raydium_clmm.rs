@@ -0,0 +1,41 @@
+use crate::amm_math::clmm::{calc_clmm_price, calc_in_range_liquidity, ClmmPoolState};
+use crate::models::Price;
+use crate::pool_pricer::PoolPricer;
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use tracing::debug;
+
+pub const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+/// The whole price of a CLMM pool lives in its `PoolState`, so no other accounts are read.
+pub struct RaydiumClmmPricer;
+
+#[async_trait]
+impl PoolPricer for RaydiumClmmPricer {
+    fn program_id(&self) -> Pubkey {
+        RAYDIUM_CLMM_PROGRAM_ID
+    }
+
+    async fn dependent_accounts(&self, _pool_pubkey: &Pubkey, _pool_account: &Account) -> anyhow::Result<Vec<Pubkey>> {
+        Ok(vec![])
+    }
+
+    async fn price(&self, pool_pubkey: &Pubkey, pool_account: &Account, _slot: Slot) -> anyhow::Result<Price> {
+        let pool = ClmmPoolState::decode(&pool_account.data)?;
+        let in_range = calc_in_range_liquidity(&pool);
+        debug!(
+            "CLMM pool '{pool_pubkey}' in-range liquidity {liquidity} in ticks [{tick_lower}, {tick_upper}): \
+             {amount_0} token 0, {amount_1} token 1",
+            liquidity = in_range.liquidity,
+            tick_lower = in_range.tick_lower,
+            tick_upper = in_range.tick_upper,
+            amount_0 = in_range.amount_0,
+            amount_1 = in_range.amount_1,
+        );
+
+        Ok(calc_clmm_price(&pool))
+    }
+}
@@ -0,0 +1,45 @@
+use crate::amm_math::cpmm::{calc_cpmm_price, load_cpmm_reserves, CpmmPoolState};
+use crate::amm_math::JsonRpcAccountReceiverClient;
+use crate::models::Price;
+use crate::pool_pricer::PoolPricer;
+use anyhow::ensure;
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+pub const RAYDIUM_CPMM_PROGRAM_ID: Pubkey = pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+
+pub struct RaydiumCpmmPricer {
+    client: Arc<JsonRpcAccountReceiverClient>,
+}
+
+impl RaydiumCpmmPricer {
+    pub fn new(client: Arc<JsonRpcAccountReceiverClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl PoolPricer for RaydiumCpmmPricer {
+    fn program_id(&self) -> Pubkey {
+        RAYDIUM_CPMM_PROGRAM_ID
+    }
+
+    async fn dependent_accounts(&self, _pool_pubkey: &Pubkey, pool_account: &Account) -> anyhow::Result<Vec<Pubkey>> {
+        let pool = CpmmPoolState::decode(&pool_account.data)?;
+
+        Ok(vec![pool.token_0_vault, pool.token_1_vault])
+    }
+
+    async fn price(&self, pool_pubkey: &Pubkey, pool_account: &Account, slot: Slot) -> anyhow::Result<Price> {
+        let pool = CpmmPoolState::decode(&pool_account.data)?;
+        // Reserves of a pool that can't be traded don't make a market price.
+        ensure!(pool.swap_enabled(), "swaps are disabled in pool '{pool_pubkey}'");
+        let reserves = load_cpmm_reserves(self.client.clone(), &pool, Some(slot)).await?;
+
+        calc_cpmm_price(&pool, &reserves)
+    }
+}
@@ -0,0 +1,130 @@
+use crate::amm_math::decode::decode_amm_info;
+use crate::amm_math::metadata::PoolMetadataCache;
+use crate::amm_math::{calc_coin_in_pc, load_pool_state, JsonRpcAccountReceiverClient};
+use crate::models::Price;
+use crate::pool_pricer::PoolPricer;
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+pub const RAYDIUM_AMM_V4_PROGRAM_ID: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+
+pub struct RaydiumV4Pricer {
+    client: Arc<JsonRpcAccountReceiverClient>,
+    metadata_cache: PoolMetadataCache,
+}
+
+impl RaydiumV4Pricer {
+    pub fn new(client: Arc<JsonRpcAccountReceiverClient>) -> Self {
+        Self { client, metadata_cache: PoolMetadataCache::default() }
+    }
+}
+
+#[async_trait]
+impl PoolPricer for RaydiumV4Pricer {
+    fn program_id(&self) -> Pubkey {
+        RAYDIUM_AMM_V4_PROGRAM_ID
+    }
+
+    async fn dependent_accounts(&self, pool_pubkey: &Pubkey, pool_account: &Account) -> anyhow::Result<Vec<Pubkey>> {
+        let amm_info = decode_amm_info(&pool_account.owner, &RAYDIUM_AMM_V4_PROGRAM_ID, &pool_account.data)?;
+        let metadata = self
+            .metadata_cache
+            .get_or_load(self.client.clone(), &RAYDIUM_AMM_V4_PROGRAM_ID, pool_pubkey, &amm_info)
+            .await?;
+
+        Ok(vec![
+            metadata.amm_keys.amm_pc_vault,
+            metadata.amm_keys.amm_coin_vault,
+            metadata.amm_keys.amm_open_order,
+            metadata.amm_keys.market,
+            *metadata.market_keys.event_q,
+        ])
+    }
+
+    async fn price(&self, pool_pubkey: &Pubkey, pool_account: &Account, slot: Slot) -> anyhow::Result<Price> {
+        let pool_state = load_pool_state(
+            self.client.clone(),
+            &self.metadata_cache,
+            &pool_account.owner,
+            &pool_account.data,
+            &RAYDIUM_AMM_V4_PROGRAM_ID,
+            pool_pubkey,
+            slot,
+        )
+        .await?;
+
+        calc_coin_in_pc(&pool_state.pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm_math::test_pool::{TestPool, ORDERBOOK_STATUS};
+    use crate::rpc::account_cache::AccountStateCache;
+    use crate::rpc::{AccountUpdate, JsonRpcAccountReceiver};
+    use solana_program::program_pack::Pack;
+    use std::str::FromStr;
+
+    fn amm_account(pool: &TestPool) -> Account {
+        Account {
+            data: pool.amm_info_data(),
+            owner: RAYDIUM_AMM_V4_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn prices_orderbook_pool() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let pricer = RaydiumV4Pricer::new(pool.accounts.clone());
+
+        let pool_account = amm_account(&pool);
+
+        let dependent_accounts = pricer.dependent_accounts(&pool.pool, &pool_account).await.unwrap();
+        assert_eq!(dependent_accounts[3..], [pool.market, pool.event_q]);
+        assert_eq!(pricer.price(&pool.pool, &pool_account, 100).await.unwrap(), Price::from_str("150").unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_accounts_of_other_programs() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let pricer = RaydiumV4Pricer::new(pool.accounts.clone());
+        let pool_account = Account { owner: Pubkey::new_unique(), ..amm_account(&pool) };
+
+        assert!(pricer.dependent_accounts(&pool.pool, &pool_account).await.is_err());
+        assert!(pricer.price(&pool.pool, &pool_account, 100).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn prices_with_dependencies_no_older_than_the_pool_update() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let cache = Arc::new(AccountStateCache::new(pool.accounts.clone()));
+        let pricer = RaydiumV4Pricer::new(cache.clone());
+        let pool_account = amm_account(&pool);
+
+        let pc_vault = pool.amm_info.pc_vault;
+        let vault_data = pool.accounts.get_account_data(&pc_vault).await.unwrap();
+        let mut vault = spl_token::state::Account::unpack(&vault_data).unwrap();
+        vault.amount = 270_000_000_000;
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        vault.pack_into_slice(&mut data);
+        let vault_update = |slot| AccountUpdate {
+            pubkey: pc_vault,
+            owner: spl_token::id(),
+            data: data.clone(),
+            slot,
+            write_version: 0,
+        };
+
+        cache.update(&vault_update(90)).await;
+        assert_eq!(pricer.price(&pool.pool, &pool_account, 100).await.unwrap(), Price::from_str("150").unwrap());
+
+        cache.update(&vault_update(100)).await;
+        assert_eq!(pricer.price(&pool.pool, &pool_account, 100).await.unwrap(), Price::from_str("250").unwrap());
+    }
+}
@@ -1,26 +1,66 @@
-use crate::amm_math::{calc_coin_in_pc, load_pool_state};
-use crate::config::Settings;
-use crate::trait_ext::duration_ext::DurationExt;
-use crate::models::PoolAndPrice;
-use crate::rpc::yellowstone_grpc::{get_account_data, AccountDataReceiverConf};
-use crate::rpc::JsonRpcAccountReceiver;
+mod new_pools;
+mod program_pools;
+mod tracked_pools;
+
+use crate::amm_math::load_pool_state;
+use crate::amm_math::metadata::PoolMetadataCache;
+use crate::amm_math::order_book::{load_order_book, L2Book};
+use crate::amm_math::quote::{quote_swap, SwapAmount, SwapDirection, SwapQuote};
+use crate::amm_math::ray_log::trades;
+use crate::config::{Settings, StreamBackend};
+use crate::models::{NewPool, PoolAndPrice, Price, PriceGap};
+use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::pool_pricer::PoolPricerRegistry;
+use crate::price_fetcher::new_pools::NewPoolWatch;
+use crate::price_fetcher::program_pools::{PoolCriteria, ProgramPools};
+use crate::price_fetcher::tracked_pools::TrackedPools;
+use crate::rpc::account_cache::AccountStateCache;
+use crate::rpc::capture::{Capture, CaptureRecord, CaptureWriter, RecordingAccountReceiver, RpcConsumer};
+use crate::rpc::multiplexer::AccountStreamMultiplexer;
+use crate::rpc::polling::{poll_disconnected_streams, PollingConf};
+use crate::rpc::subscription::TransactionFilter;
+use crate::rpc::supervisor::ConnectionState;
+use crate::rpc::{AccountUpdate, JsonRpcAccountReceiver, StreamEvent, TransactionUpdate};
 use crate::storage::{OldRecordCleaner, PoolPriceStorage};
+use crate::trait_ext::arced_ext::Arced;
+use crate::trait_ext::duration_ext::DurationExt;
+use anyhow::{ensure, Context};
 use chrono::Utc;
 use serde::Deserialize;
+use solana_sdk::clock::Slot;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
 use std::fmt::Display;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::spawn;
-use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+
+/// Name of the stream filter carrying every pool of the program-wide subscription.
+const PROGRAM_POOLS_FILTER: &str = "raydium-amm-v4-pools";
+/// Name of the stream filter carrying the transactions pools are created by.
+const NEW_POOLS_FILTER: &str = "raydium-amm-v4-transactions";
+/// Name of the stream filter carrying the transactions of the tracked pools.
+const TRADES_FILTER: &str = "raydium-amm-v4-trades";
 
 #[derive(Clone)]
 pub struct PriceFetchService {
     old_record_cleaner: Arc<dyn OldRecordCleaner + Sync + Send>,
     storage: Arc<dyn PoolPriceStorage + Sync + Send>,
+    /// Reads of the tracked pools, the other components read through their own receivers.
     json_rpc_account_receiver: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+    query_account_receiver: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+    polling_account_receiver: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+    account_cache: Arc<AccountStateCache>,
+    /// Metadata of the pools quoted through the bot.
+    quote_metadata_cache: Arc<PoolMetadataCache>,
+    pool_pricers: Arc<PoolPricerRegistry>,
+    account_streams: Arc<AccountStreamMultiplexer>,
+    capture_writer: Option<CaptureWriter>,
+    program_pools: Option<Arc<ProgramPools>>,
+    new_pool_watch: Option<Arc<NewPoolWatch>>,
     config: Settings,
     liquidity_pools_account_addresses: Vec<Pubkey>,
 }
@@ -32,8 +72,32 @@ impl PriceFetchService {
         storage: Arc<dyn PoolPriceStorage + Sync + Send>,
         json_rpc_account_receiver: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
     ) -> anyhow::Result<Self> {
+        ensure!(
+            !settings.all_pools.enabled || settings.rpc.stream_backend == StreamBackend::YellowstoneGrpc,
+            "streaming all pools requires the Yellowstone gRPC backend"
+        );
+        ensure!(
+            !settings.new_pools.enabled || settings.rpc.stream_backend == StreamBackend::YellowstoneGrpc,
+            "detecting new pools requires the Yellowstone gRPC backend"
+        );
+        ensure!(
+            !settings.trades.enabled || settings.rpc.stream_backend == StreamBackend::YellowstoneGrpc,
+            "streaming trades requires the Yellowstone gRPC backend"
+        );
+        let capture_writer = match &settings.recorder.capture_path {
+            Some(path) => Some(CaptureWriter::create(path).await?),
+            None => None,
+        };
+        let account_receivers = |consumer| -> Arc<dyn JsonRpcAccountReceiver + Send + Sync> {
+            match &capture_writer {
+                Some(writer) => {
+                    RecordingAccountReceiver::new(json_rpc_account_receiver.clone(), writer.clone(), consumer).arced()
+                }
+                None => json_rpc_account_receiver.clone(),
+            }
+        };
         let fetcher =
-            Self::new(settings.clone(), old_record_cleaner, storage, json_rpc_account_receiver);
+            Self::new(settings.clone(), old_record_cleaner, storage, account_receivers, capture_writer.clone());
 
         if fetcher.config.database.clear_old_records {
             fetcher
@@ -45,12 +109,15 @@ impl PriceFetchService {
         Ok(fetcher)
     }
 
+    /// `account_receivers` gives the JSON-RPC receiver each component reads through.
     fn new(
         settings: Settings,
         old_record_cleaner: Arc<dyn OldRecordCleaner + Sync + Send>,
         storage: Arc<dyn PoolPriceStorage + Sync + Send>,
-        json_rpc_account_receiver: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+        account_receivers: impl Fn(RpcConsumer) -> Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+        capture_writer: Option<CaptureWriter>,
     ) -> Self {
+        let json_rpc_account_receiver = account_receivers(RpcConsumer::TrackedPools);
         let liquidity_pools_account_addresses = settings
             .liquidity_pool
             .account_addresses_base54
@@ -58,91 +125,546 @@ impl PriceFetchService {
             .map(|pubkey| Pubkey::from_str(pubkey).expect(&format!("Failed to parse pubkey: {pubkey}")))
             .collect();
 
+        let account_cache = AccountStateCache::new(json_rpc_account_receiver.clone()).arced();
+        let program_pools = settings.all_pools.enabled.then(|| {
+            let criteria = PoolCriteria::from(&settings.all_pools);
+            let client = account_receivers(RpcConsumer::ProgramPools);
+            ProgramPools::new(client, criteria, settings.all_pools.max_pricings_per_second).arced()
+        });
+        let new_pool_watch = settings
+            .new_pools
+            .enabled
+            .then(|| NewPoolWatch::from(&settings.new_pools).arced());
+
         Self {
             old_record_cleaner,
             storage,
+            pool_pricers: PoolPricerRegistry::with_default_pricers(account_cache.clone()).arced(),
+            account_cache,
+            quote_metadata_cache: PoolMetadataCache::default().arced(),
+            account_streams: AccountStreamMultiplexer::new(settings.rpc.clone()).arced(),
             json_rpc_account_receiver,
+            query_account_receiver: account_receivers(RpcConsumer::Queries),
+            polling_account_receiver: account_receivers(RpcConsumer::Polling),
+            capture_writer,
+            program_pools,
+            new_pool_watch,
             config: settings,
             liquidity_pools_account_addresses,
         }
     }
 
     pub fn supported_pools(&self) -> Vec<String> {
-        self.liquidity_pools_account_addresses.iter().map(Pubkey::to_string).collect()
+        self.liquidity_pools_account_addresses
+            .iter()
+            .map(Pubkey::to_string)
+            .collect()
     }
 
+    /// First listed pool, the one bot commands without a pool argument are about.
+    pub fn default_pool(&self) -> Option<Pubkey> {
+        self.liquidity_pools_account_addresses.first().copied()
+    }
+
+    /// Pools detected from now on, `None` when pool detection is disabled.
+    pub fn new_pools(&self) -> Option<broadcast::Receiver<NewPool>> {
+        Some(self.new_pool_watch.as_ref()?.subscribe())
+    }
 
     pub async fn current(&self, pool_pubkey: &Pubkey) -> PriceFetchResponse {
         let pool_pubkey_as_string = pool_pubkey.to_string();
 
         match self.storage.current(pool_pubkey).await {
-            Ok(zero) if zero == -1.0 => PriceFetchResponse::no_data_found(&pool_pubkey_as_string),
-            Ok(res) => PriceFetchResponse::current(&pool_pubkey_as_string, res),
+            Ok(None) => match self.live_price(pool_pubkey).await {
+                Some(price) => PriceFetchResponse::current(&pool_pubkey_as_string, price),
+                None => PriceFetchResponse::no_data_found(&pool_pubkey_as_string),
+            },
+            Ok(Some(res)) => PriceFetchResponse::current(&pool_pubkey_as_string, res),
             Err(e) => PriceFetchResponse::generic_err(&pool_pubkey_as_string, e.to_string()),
         }
     }
 
+    /// In-memory price of a pool streamed by the program-wide subscription, such pools may not be persisted.
+    async fn live_price(&self, pool_pubkey: &Pubkey) -> Option<Price> {
+        Some(self.program_pools.as_ref()?.current(pool_pubkey).await?.price)
+    }
+
     pub async fn average(&self, pool_pubkey: &Pubkey) -> PriceFetchResponse {
         // TODO: Provide configurable `for_interval` variable
         let (pool_pubkey_as_string, for_interval) = (pool_pubkey.to_string(), Duration::from_minutes(5));
 
         match self.storage.average(pool_pubkey, for_interval).await {
-            Ok(zero) if zero == -1.0 => PriceFetchResponse::no_data_found(&pool_pubkey_as_string),
-            Ok(res) => PriceFetchResponse::average(&pool_pubkey_as_string, res, for_interval),
+            Ok(None) => PriceFetchResponse::no_data_found(&pool_pubkey_as_string),
+            Ok(Some(res)) => PriceFetchResponse::average(&pool_pubkey_as_string, res, for_interval),
+            Err(e) => PriceFetchResponse::generic_err(&pool_pubkey_as_string, e.to_string()),
+        }
+    }
+
+    pub async fn order_book(&self, pool_pubkey: &Pubkey) -> PriceFetchResponse {
+        let pool_pubkey_as_string = pool_pubkey.to_string();
+
+        match load_order_book(self.query_account_receiver.clone(), &RAYDIUM_AMM_V4_PROGRAM_ID, pool_pubkey).await {
+            Ok(book) => {
+                let amm_price = match self.storage.current(pool_pubkey).await {
+                    Ok(Some(price)) => Some(price),
+                    Ok(None) => self.live_price(pool_pubkey).await,
+                    Err(e) => return PriceFetchResponse::generic_err(&pool_pubkey_as_string, e.to_string()),
+                };
+                PriceFetchResponse::order_book(&pool_pubkey_as_string, OrderBookSummary::new(&book, amm_price))
+            }
             Err(e) => PriceFetchResponse::generic_err(&pool_pubkey_as_string, e.to_string()),
         }
     }
 
+    /// Quote of a swap against the latest reserves of a Raydium AMM v4 pool, read through JSON-RPC.
+    pub async fn quote(
+        &self,
+        pool_pubkey: &Pubkey,
+        direction: SwapDirection,
+        amount: SwapAmount,
+    ) -> PriceFetchResponse {
+        let pool_pubkey_as_string = pool_pubkey.to_string();
+
+        match self.load_quote(pool_pubkey, direction, amount).await {
+            Ok(quote) => PriceFetchResponse::quote(&pool_pubkey_as_string, quote),
+            Err(e) => PriceFetchResponse::generic_err(&pool_pubkey_as_string, e.to_string()),
+        }
+    }
+
+    async fn load_quote(
+        &self,
+        pool_pubkey: &Pubkey,
+        direction: SwapDirection,
+        amount: SwapAmount,
+    ) -> anyhow::Result<SwapQuote> {
+        let (slot, pool_accounts) = self
+            .query_account_receiver
+            .get_multiple_accounts_with_slot(&[*pool_pubkey], None)
+            .await?;
+        let pool_account = pool_accounts.first().context("pool account is missing")?;
+        ensure!(
+            pool_account.owner == RAYDIUM_AMM_V4_PROGRAM_ID,
+            "quotes are only supported for Raydium AMM v4 pools"
+        );
+
+        let pool_state = load_pool_state(
+            self.query_account_receiver.clone(),
+            &self.quote_metadata_cache,
+            &pool_account.owner,
+            &pool_account.data,
+            &RAYDIUM_AMM_V4_PROGRAM_ID,
+            pool_pubkey,
+            slot,
+        )
+        .await?;
+
+        quote_swap(&pool_state, direction, amount)
+    }
+
+    pub async fn connection_status(&self, pool_pubkey: &Pubkey) -> PriceFetchResponse {
+        let pool_pubkey_as_string = pool_pubkey.to_string();
+
+        match self.account_streams.connection_state(&pool_pubkey_as_string).await {
+            Some(state) => PriceFetchResponse::connection_status(&pool_pubkey_as_string, state),
+            None => PriceFetchResponse::no_data_found(&pool_pubkey_as_string),
+        }
+    }
+
     pub fn start_price_fetching_in_background(&self) -> anyhow::Result<()> {
         spawn(self.clone().start_price_fetch());
         Ok(())
     }
 
     pub async fn start_price_fetch(self) -> anyhow::Result<()> {
-        let (tx, mut rx) = mpsc::channel::<(Pubkey, Vec<u8>)>(32);
-
-        // TODO: Add supervisor for gRPC Yellowstone data receiver.
-        for address in self.liquidity_pools_account_addresses {
-            let account_data_receiver_conf = AccountDataReceiverConf {
-                account_address: address,
-                sender: tx.clone(),
-                yellowstone_grpc_endpoint: self.config.rpc.yellowstone_grpc_endpoint.clone(),
-            };
+        let (tx, mut rx) = mpsc::channel::<StreamEvent>(32);
+        let mut tracked_pools = TrackedPools::default();
+        self.account_streams.start(tx.clone()).await?;
+        spawn(poll_disconnected_streams(
+            PollingConf::from(&self.config.rpc),
+            self.polling_account_receiver.clone(),
+            self.account_streams.clone(),
+            tx,
+        ));
 
-            spawn(get_account_data(account_data_receiver_conf));
+        for address in &self.liquidity_pools_account_addresses {
+            if let Some(account_addresses) = self.track_pool(&mut tracked_pools, address).await {
+                self.account_streams
+                    .set(&address.to_string(), account_addresses)
+                    .await?;
+            }
         }
 
-        while let Some((pool_address, account_data)) = rx.recv().await {
-            info!("Successfully received data from: {pool_address}", pool_address = pool_address.to_string());
+        if let Some(program_pools) = &self.program_pools {
+            spawn(program_pools.clone().run(self.storage.clone(), self.listed_pools()));
+            self.account_streams
+                .set_program(PROGRAM_POOLS_FILTER, ProgramPools::filter())
+                .await?;
+            info!("Subscribed to all Raydium AMM v4 pools");
+        }
+
+        if self.new_pool_watch.is_some() {
+            self.account_streams
+                .set_transactions(NEW_POOLS_FILTER, NewPoolWatch::filter())
+                .await?;
+            info!("Watching Raydium AMM v4 transactions for new pools");
+        }
+
+        if self.config.trades.enabled {
+            self.subscribe_to_trades(&tracked_pools).await?;
+        }
 
-            let Ok(price) =
-                load_pool_state(self.json_rpc_account_receiver.clone(), account_data, &Pubkey::from_str("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8").unwrap(), &pool_address)
+        while let Some(event) = rx.recv().await {
+            if let Some(writer) = &self.capture_writer {
+                writer
+                    .record(&CaptureRecord::from_event(&event))
                     .await
-                    .inspect_err(|e| error!("Failed to load pool state: {e}"))
-                    .map(|state| calc_coin_in_pc(&state.pool))
-            else {
+                    .inspect_err(|e| error!("Failed to record stream event: {e}"))
+                    .ok();
+            }
+
+            match event {
+                StreamEvent::Account(update) => self.dispatch_account_update(&mut tracked_pools, update).await,
+                StreamEvent::Transaction(transaction) => {
+                    self.handle_transaction(&mut tracked_pools, &transaction).await
+                }
+                StreamEvent::Resubscribed { stream_name, last_slot } => {
+                    warn!("Stream '{stream_name}' resubscribed after slot {last_slot:?}, resynchronizing its pools");
+                    let pools = self
+                        .account_streams
+                        .filters_of(&stream_name)
+                        .await
+                        .iter()
+                        .filter_map(|filter_name| Pubkey::from_str(filter_name).ok())
+                        .collect::<Vec<_>>();
+
+                    self.resynchronize(&tracked_pools, &pools, last_slot)
+                        .await
+                        .inspect_err(|e| error!("Failed to resynchronize pools: {e}"))
+                        .ok();
+                }
+            }
+        }
+
+        warn!("Liquidity pool price fetcher stopped!");
+
+        Ok(())
+    }
+
+    /// Feeds the stream events of `capture` through the pricing pipeline, JSON-RPC reads are answered from the capture
+    /// as well, so nothing goes over the network. Returns once every event is handled.
+    pub async fn replay(
+        settings: Settings,
+        old_record_cleaner: Arc<dyn OldRecordCleaner + Sync + Send>,
+        storage: Arc<dyn PoolPriceStorage + Sync + Send>,
+        capture: &Capture,
+    ) -> anyhow::Result<()> {
+        let account_receivers =
+            |consumer| -> Arc<dyn JsonRpcAccountReceiver + Send + Sync> { capture.account_receiver(consumer).arced() };
+        let fetcher = Self::new(settings, old_record_cleaner, storage, account_receivers, None);
+        let (mut tracked_pools, listed_pools) = (TrackedPools::default(), fetcher.listed_pools());
+
+        for address in &fetcher.liquidity_pools_account_addresses {
+            fetcher.track_pool(&mut tracked_pools, address).await;
+        }
+
+        for event in capture.events() {
+            match event {
+                StreamEvent::Account(update) => {
+                    fetcher.dispatch_account_update(&mut tracked_pools, update).await;
+                    // Priced right away rather than in the background, so the replay is deterministic.
+                    if let Some(program_pools) = &fetcher.program_pools {
+                        program_pools.price_pending(&fetcher.storage, &listed_pools).await;
+                    }
+                }
+                StreamEvent::Transaction(transaction) => {
+                    fetcher.handle_transaction(&mut tracked_pools, &transaction).await
+                }
+                // There are no streams to ask for their pools, all of them are resynchronized.
+                StreamEvent::Resubscribed { last_slot, .. } => {
+                    let pools = &fetcher.liquidity_pools_account_addresses;
+                    fetcher.resynchronize(&tracked_pools, pools, last_slot).await?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Listed pools are persisted by the regular pipeline, so the program-wide subscription skips them.
+    fn listed_pools(&self) -> HashSet<Pubkey> {
+        self.liquidity_pools_account_addresses.iter().copied().collect()
+    }
+
+    /// Returns the accounts the pool is tracked by, `None` when they can't be resolved.
+    async fn track_pool(&self, tracked_pools: &mut TrackedPools, address: &Pubkey) -> Option<Vec<Pubkey>> {
+        let account_addresses = self
+            .tracked_accounts(address)
+            .await
+            .inspect_err(|e| error!("Failed to resolve accounts of pool '{address}': {e}"))
+            .ok()?;
+
+        tracked_pools.track(*address, account_addresses.clone());
+        Some(account_addresses)
+    }
+
+    /// Updates of the program-wide subscription are queued for pricing, only the ones of tracked pools go on.
+    async fn dispatch_account_update(&self, tracked_pools: &mut TrackedPools, update: AccountUpdate) {
+        if let Some(program_pools) = self.program_pools.as_ref().filter(|pools| pools.accepts(&update)) {
+            program_pools.enqueue(update.clone());
+            // Other pools of the program are priced in memory only, without caching their accounts.
+            if tracked_pools.pools_of(&update.pubkey).is_empty() {
+                return;
+            }
+        }
+
+        self.handle_account_update(tracked_pools, update).await
+    }
+
+    async fn handle_account_update(&self, tracked_pools: &mut TrackedPools, update: AccountUpdate) {
+        debug!("Successfully received data from: {account_address}", account_address = update.pubkey.to_string());
+        if !self.account_cache.update(&update).await {
+            debug!("Skipped stale update of '{pubkey}' at slot {slot}", pubkey = update.pubkey, slot = update.slot);
+            return;
+        }
+
+        for pool_address in tracked_pools.pools_of(&update.pubkey) {
+            if pool_address == update.pubkey {
+                self.refresh_tracked_accounts(tracked_pools, &pool_address).await;
+            }
+
+            self.price_and_save(&pool_address, update.slot).await;
+        }
+    }
+
+    /// Persists the swaps of the tracked pools and announces the pools of interest created by the transaction, they
+    /// are tracked as well when configured to.
+    async fn handle_transaction(&self, tracked_pools: &mut TrackedPools, transaction: &TransactionUpdate) {
+        if self.config.trades.enabled {
+            self.save_trades(tracked_pools, transaction).await;
+        }
+
+        let Some(new_pool_watch) = &self.new_pool_watch else {
+            return;
+        };
+
+        for new_pool in new_pool_watch.detect(transaction) {
+            let pool_pubkey = new_pool.pool_pubkey;
+            info!("Detected new pool '{pool_pubkey}' at slot {slot}", slot = new_pool.slot);
+
+            if new_pool_watch.auto_track && tracked_pools.accounts_of(&pool_pubkey).is_empty() {
+                if let Some(account_addresses) = self.track_pool(tracked_pools, &pool_pubkey).await {
+                    self.account_streams
+                        .set(&pool_pubkey.to_string(), account_addresses)
+                        .await
+                        .inspect_err(|e| error!("Failed to subscribe to new pool '{pool_pubkey}': {e}"))
+                        .ok();
+
+                    if self.config.trades.enabled {
+                        self.subscribe_to_trades(tracked_pools)
+                            .await
+                            .inspect_err(|e| error!("Failed to subscribe to trades of '{pool_pubkey}': {e}"))
+                            .ok();
+                    }
+                }
+            }
+
+            new_pool_watch.announce(new_pool);
+        }
+    }
+
+    async fn save_trades(&self, tracked_pools: &TrackedPools, transaction: &TransactionUpdate) {
+        let recorded_at = Utc::now().naive_utc();
+
+        // Transactions may route through untracked pools as well.
+        for trade in trades(transaction, recorded_at) {
+            if tracked_pools.accounts_of(&trade.pool_pubkey).is_empty() {
                 continue;
-            };
+            }
 
             self.storage
-                .save(&PoolAndPrice { pool_pubkey: pool_address, price, updated_at: Utc::now().naive_utc() })
+                .save_trade(&trade)
                 .await
-                .inspect_err(|e| error!("Failed to save price for '{pool_address}'. Cause: {e:?}"))
+                .inspect_err(|e| error!("Failed to save trade of '{pool}'. Cause: {e:?}", pool = trade.pool_pubkey))
                 .ok();
         }
+    }
 
-        warn!("Liquidity pool price fetcher stopped!");
+    /// Streams the transactions touching the tracked Raydium AMM v4 pools, other pools don't emit `ray_log` events.
+    async fn subscribe_to_trades(&self, tracked_pools: &TrackedPools) -> anyhow::Result<()> {
+        let mut pools = vec![];
+        for pool in tracked_pools.pools() {
+            // Accounts of tracked pools are cached, so this doesn't go over the network.
+            if self.account_cache.get_account(pool, None).await?.owner == RAYDIUM_AMM_V4_PROGRAM_ID {
+                pools.push(*pool);
+            }
+        }
+
+        // Without any account to include, every transaction of the chain would be streamed.
+        if pools.is_empty() {
+            return self.account_streams.remove(TRADES_FILTER).await;
+        }
+
+        pools.sort();
+        self.account_streams
+            .set_transactions(TRADES_FILTER, TransactionFilter { accounts: pools })
+            .await
+    }
+
+    /// Updates missed while a stream was down can't be replayed, so every tracked account is read once through
+    /// JSON-RPC, pools are repriced from that snapshot and the missed slots are recorded as a gap.
+    async fn resynchronize(
+        &self,
+        tracked_pools: &TrackedPools,
+        pools: &[Pubkey],
+        last_slot: Option<Slot>,
+    ) -> anyhow::Result<()> {
+        let mut accounts = pools
+            .iter()
+            .flat_map(|pool| tracked_pools.accounts_of(pool))
+            .collect::<Vec<_>>();
+        accounts.sort();
+        accounts.dedup();
+        if accounts.is_empty() {
+            return Ok(());
+        }
+
+        let (snapshot_slot, snapshot) = self
+            .json_rpc_account_receiver
+            .get_multiple_accounts_with_slot(&accounts, last_slot)
+            .await?;
+
+        for (pubkey, account) in accounts.into_iter().zip(snapshot) {
+            let update = AccountUpdate {
+                pubkey,
+                owner: account.owner,
+                data: account.data,
+                slot: snapshot_slot,
+                write_version: 0,
+            };
+            self.account_cache.update(&update).await;
+        }
+
+        let detected_at = Utc::now().naive_utc();
+        for pool_address in pools {
+            self.storage
+                .save_gap(&PriceGap::new(*pool_address, last_slot, snapshot_slot, detected_at))
+                .await
+                .inspect_err(|e| error!("Failed to save price gap for '{pool_address}'. Cause: {e:?}"))
+                .ok();
+
+            self.price_and_save(pool_address, snapshot_slot).await;
+        }
 
+        info!("Resynchronized pools from the snapshot at slot {snapshot_slot}");
         Ok(())
     }
+
+    async fn price_and_save(&self, pool_address: &Pubkey, slot: Slot) {
+        let Ok(price) = self
+            .price_pool(pool_address, slot)
+            .await
+            .inspect_err(|e| error!("Failed to price pool '{pool_address}': {e}"))
+        else {
+            return;
+        };
+
+        self.storage
+            .save(&PoolAndPrice::new(*pool_address, price, slot, Utc::now().naive_utc()))
+            .await
+            .inspect_err(|e| error!("Failed to save price for '{pool_address}'. Cause: {e:?}"))
+            .ok();
+    }
+
+    /// Pool accounts may point to other dependencies after an update, e.g. when an AMM is moved to another market.
+    async fn refresh_tracked_accounts(&self, tracked_pools: &mut TrackedPools, pool_pubkey: &Pubkey) {
+        let Ok(account_addresses) = self
+            .tracked_accounts(pool_pubkey)
+            .await
+            .inspect_err(|e| error!("Failed to resolve accounts of pool '{pool_pubkey}': {e}"))
+        else {
+            return;
+        };
+
+        if tracked_pools.track(*pool_pubkey, account_addresses.clone()) {
+            info!("Accounts of pool '{pool_pubkey}' changed, updating the subscription");
+            self.account_streams
+                .set(&pool_pubkey.to_string(), account_addresses)
+                .await
+                .inspect_err(|e| error!("Failed to update subscription of pool '{pool_pubkey}': {e}"))
+                .ok();
+        }
+    }
+
+    /// Pool account together with the accounts its pricer reads.
+    async fn tracked_accounts(&self, pool_pubkey: &Pubkey) -> anyhow::Result<Vec<Pubkey>> {
+        let pool_account = self.account_cache.get_account(pool_pubkey, None).await?;
+        let pool_pricer = self
+            .pool_pricers
+            .get(&pool_account.owner)
+            .with_context(|| format!("no pricer for program '{owner}'", owner = pool_account.owner))?;
+
+        let mut accounts = vec![*pool_pubkey];
+        accounts.extend(pool_pricer.dependent_accounts(pool_pubkey, &pool_account).await?);
+        Ok(accounts)
+    }
+
+    /// Prices the pool as of `slot`, the slot of the update that triggered the pricing.
+    async fn price_pool(&self, pool_pubkey: &Pubkey, slot: Slot) -> anyhow::Result<Price> {
+        let pool_account = self.account_cache.get_account(pool_pubkey, Some(slot)).await?;
+        let pool_pricer = self
+            .pool_pricers
+            .get(&pool_account.owner)
+            .with_context(|| format!("no pricer for program '{owner}'", owner = pool_account.owner))?;
+
+        pool_pricer.price(pool_pubkey, &pool_account, slot).await
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OrderBookSummary {
+    pub best_bid: Option<Price>,
+    pub best_ask: Option<Price>,
+    pub mid: Option<Price>,
+    pub spread: Option<Price>,
+    pub amm_price: Option<Price>,
+}
+
+impl OrderBookSummary {
+    pub fn new(book: &L2Book, amm_price: Option<Price>) -> Self {
+        Self {
+            best_bid: book.best_bid().cloned(),
+            best_ask: book.best_ask().cloned(),
+            mid: book.mid(),
+            spread: book.spread(),
+            amm_price,
+        }
+    }
 }
 
+impl Display for OrderBookSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format_price = |price: &Option<Price>| price.as_ref().map_or("-".to_string(), Price::to_string);
+
+        write!(
+            f,
+            "Best bid: {best_bid}, best ask: {best_ask}, mid: {mid}, spread: {spread}. AMM price: {amm_price}.",
+            best_bid = format_price(&self.best_bid),
+            best_ask = format_price(&self.best_ask),
+            mid = format_price(&self.mid),
+            spread = format_price(&self.spread),
+            amm_price = format_price(&self.amm_price),
+        )
+    }
+}
 
 // TODO: Add more convenient response structs
 #[derive(Deserialize, Debug, Clone)]
 pub enum PriceFetchResponseType {
-    CurrentPrice(f64),
-    AveragePrice(f64, Duration),
+    CurrentPrice(Price),
+    AveragePrice(Price, Duration),
+    OrderBook(OrderBookSummary),
+    Quote(SwapQuote),
+    ConnectionStatus(ConnectionState),
     GenericError(String),
     NoDataFound,
 }
@@ -155,6 +677,9 @@ impl Display for PriceFetchResponseType {
                 "Average price for last {last_minutes} minutes: {price}.",
                 last_minutes = for_duration.as_minutes()
             )),
+            PriceFetchResponseType::OrderBook(summary) => summary.to_string(),
+            PriceFetchResponseType::Quote(quote) => format!("Quote: {quote}"),
+            PriceFetchResponseType::ConnectionStatus(state) => format!("Account stream is {state}."),
             PriceFetchResponseType::GenericError(err_msg) => format!("Error: {err_msg}."),
             PriceFetchResponseType::NoDataFound => "No data found.".to_string(),
         };
@@ -170,20 +695,41 @@ pub struct PriceFetchResponse {
 }
 
 impl PriceFetchResponse {
-    pub fn current(pool_address: &str, price: f64) -> Self {
+    pub fn current(pool_address: &str, price: Price) -> Self {
         Self {
             pool_address: pool_address.to_string(),
             response_type: PriceFetchResponseType::CurrentPrice(price),
         }
     }
 
-    pub fn average(pool_address: &str, price: f64, for_intrval: Duration) -> Self {
+    pub fn average(pool_address: &str, price: Price, for_intrval: Duration) -> Self {
         Self {
             pool_address: pool_address.to_string(),
             response_type: PriceFetchResponseType::AveragePrice(price, for_intrval),
         }
     }
 
+    pub fn order_book(pool_address: &str, summary: OrderBookSummary) -> Self {
+        Self {
+            pool_address: pool_address.to_string(),
+            response_type: PriceFetchResponseType::OrderBook(summary),
+        }
+    }
+
+    pub fn quote(pool_address: &str, quote: SwapQuote) -> Self {
+        Self {
+            pool_address: pool_address.to_string(),
+            response_type: PriceFetchResponseType::Quote(quote),
+        }
+    }
+
+    pub fn connection_status(pool_address: &str, state: ConnectionState) -> Self {
+        Self {
+            pool_address: pool_address.to_string(),
+            response_type: PriceFetchResponseType::ConnectionStatus(state),
+        }
+    }
+
     pub fn no_data_found(pool_address: &str) -> Self {
         Self {
             pool_address: pool_address.to_string(),
@@ -209,3 +755,121 @@ impl Display for PriceFetchResponse {
         write!(f, "{}", str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm_math::test_pool::{TestPool, ORDERBOOK_STATUS};
+    use crate::pool_pricer::raydium_cpmm::RAYDIUM_CPMM_PROGRAM_ID;
+    use crate::storage::MemoryStorage;
+
+    /// CP-Swap `PoolState` with 9 decimals for token 0 and 6 for token 1, see `amm_math::cpmm` for the layout.
+    fn cpmm_pool_data(vault_0: &Pubkey, vault_1: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; 637];
+        data[..8].copy_from_slice(&[247, 237, 227, 245, 215, 195, 222, 70]);
+        data[72..104].copy_from_slice(vault_0.as_ref());
+        data[104..136].copy_from_slice(vault_1.as_ref());
+        data[331] = 9;
+        data[332] = 6;
+        data
+    }
+
+    fn token_account_data(amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 165];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    #[tokio::test]
+    async fn replays_captured_updates_into_prices() {
+        let (pool, vault_0, vault_1) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let rpc_account = |pubkey, owner, data| CaptureRecord::RpcAccount {
+            consumer: RpcConsumer::TrackedPools,
+            pubkey,
+            slot: None,
+            owner,
+            lamports: 1,
+            data,
+        };
+        let update = |pubkey, slot, amount| CaptureRecord::Update {
+            pubkey,
+            owner: spl_token::id(),
+            slot,
+            write_version: 0,
+            data: token_account_data(amount),
+        };
+        let capture = Capture {
+            records: vec![
+                rpc_account(pool, RAYDIUM_CPMM_PROGRAM_ID, cpmm_pool_data(&vault_0, &vault_1)),
+                update(vault_0, 10, 2_000_000_000),
+                rpc_account(vault_1, spl_token::id(), token_account_data(300_000_000)),
+                update(vault_1, 11, 600_000_000),
+            ],
+        };
+
+        let mut settings = Settings::default().unwrap();
+        settings.liquidity_pool.account_addresses_base54 = vec![pool.to_string()];
+        let storage = Arc::new(MemoryStorage::default());
+        PriceFetchService::replay(settings, storage.clone(), storage.clone(), &capture)
+            .await
+            .unwrap();
+
+        let prices = storage.prices().await;
+        let prices = prices
+            .iter()
+            .map(|price| (price.slot, price.price.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            prices,
+            vec![
+                (10, Price::from_str("150").unwrap()),
+                (11, Price::from_str("300").unwrap())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_program_wide_updates_through_program_pools() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let dependencies = [
+            pool.amm_info.coin_vault,
+            pool.amm_info.pc_vault,
+            pool.amm_info.open_orders,
+            pool.market,
+        ];
+        let mut records = vec![];
+        for pubkey in dependencies.into_iter().chain([pool.event_q]) {
+            let account = pool.accounts.get_account(&pubkey, None).await.unwrap();
+            records.push(CaptureRecord::RpcAccount {
+                consumer: RpcConsumer::ProgramPools,
+                pubkey,
+                slot: None,
+                owner: account.owner,
+                lamports: account.lamports,
+                data: account.data,
+            });
+        }
+        records.push(CaptureRecord::Update {
+            pubkey: pool.pool,
+            owner: RAYDIUM_AMM_V4_PROGRAM_ID,
+            slot: 100,
+            write_version: 0,
+            data: pool.amm_info_data(),
+        });
+
+        let mut settings = Settings::default().unwrap();
+        settings.liquidity_pool.account_addresses_base54 = vec![];
+        settings.all_pools.enabled = true;
+        let storage = Arc::new(MemoryStorage::default());
+        PriceFetchService::replay(settings, storage.clone(), storage.clone(), &Capture { records })
+            .await
+            .unwrap();
+
+        let prices = storage.prices().await;
+        let prices = prices
+            .iter()
+            .map(|price| (price.pool_pubkey, price.slot, &price.price))
+            .collect::<Vec<_>>();
+        assert_eq!(prices, vec![(pool.pool, 100, &Price::from_str("150").unwrap())]);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::amm_math::amm_instruction::created_pools;
+use crate::config::NewPools;
+use crate::models::NewPool;
+use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::rpc::subscription::TransactionFilter;
+use crate::rpc::TransactionUpdate;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+
+/// Detected pools buffered for each announcement receiver, older ones are dropped for slow receivers.
+const ANNOUNCEMENT_CAPACITY: usize = 64;
+
+/// Detects the Raydium AMM v4 pools created for the mints of interest and announces them.
+pub struct NewPoolWatch {
+    mints: HashSet<Pubkey>,
+    pub auto_track: bool,
+    announcements: broadcast::Sender<NewPool>,
+}
+
+impl From<&NewPools> for NewPoolWatch {
+    fn from(config: &NewPools) -> Self {
+        Self {
+            mints: config.mint_allowlist.iter().copied().collect(),
+            auto_track: config.auto_track,
+            announcements: broadcast::channel(ANNOUNCEMENT_CAPACITY).0,
+        }
+    }
+}
+
+impl NewPoolWatch {
+    /// Every transaction of the AMM program, pool creations can't be told apart before they are decoded.
+    pub fn filter() -> TransactionFilter {
+        TransactionFilter { accounts: vec![RAYDIUM_AMM_V4_PROGRAM_ID] }
+    }
+
+    /// Pools of interest created by the transaction.
+    pub fn detect(&self, transaction: &TransactionUpdate) -> Vec<NewPool> {
+        created_pools(transaction)
+            .into_iter()
+            .filter(|new_pool| self.matches(new_pool))
+            .collect()
+    }
+
+    fn matches(&self, new_pool: &NewPool) -> bool {
+        let mints = [new_pool.coin_mint, new_pool.pc_mint];
+        self.mints.is_empty() || mints.iter().any(|mint| self.mints.contains(mint))
+    }
+
+    /// Nobody may be listening, the pool is dropped then.
+    pub fn announce(&self, new_pool: NewPool) {
+        self.announcements.send(new_pool).ok();
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<NewPool> {
+        self.announcements.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::ExecutedInstruction;
+    use solana_sdk::signature::Signature;
+
+    fn pool_creation(coin_mint: Pubkey, pc_mint: Pubkey) -> ExecutedInstruction {
+        let mut accounts = (0..21).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        (accounts[8], accounts[9]) = (coin_mint, pc_mint);
+        let data = [&[1, 254][..], &[0; 24]].concat();
+
+        ExecutedInstruction { program_id: RAYDIUM_AMM_V4_PROGRAM_ID, accounts, data }
+    }
+
+    #[test]
+    fn announces_pools_with_allowlisted_mints() {
+        let (wanted_mint, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let config = NewPools { mint_allowlist: vec![wanted_mint], ..NewPools::default() };
+        let watch = NewPoolWatch::from(&config);
+        let mut announcements = watch.subscribe();
+
+        let transaction = TransactionUpdate {
+            signature: Signature::new_unique(),
+            slot: 42,
+            account_keys: vec![],
+            instructions: vec![
+                pool_creation(other_mint, wanted_mint),
+                pool_creation(other_mint, Pubkey::new_unique()),
+            ],
+            log_messages: vec![],
+        };
+        let [new_pool] = watch.detect(&transaction).try_into().unwrap();
+        watch.announce(new_pool.clone());
+
+        assert_eq!(new_pool.pc_mint, wanted_mint);
+        assert_eq!(announcements.try_recv().unwrap(), new_pool);
+    }
+}
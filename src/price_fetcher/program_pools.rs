@@ -0,0 +1,268 @@
+use crate::amm_math::metadata::PoolMetadataCache;
+use crate::amm_math::{calc_coin_in_pc, load_pool_state, JsonRpcAccountReceiverClient};
+use crate::config::AllPools;
+use crate::models::{PoolAndPrice, Price};
+use crate::pool_pricer::raydium_v4::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::rpc::resilient::RateLimiter;
+use crate::rpc::subscription::ProgramFilter;
+use crate::rpc::AccountUpdate;
+use crate::storage::PoolPriceStorage;
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use futures::stream::{iter, StreamExt};
+use raydium_amm::state::AmmInfo;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::mem::{size_of, take};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, RwLock};
+use tracing::{debug, error, warn};
+
+/// Pools priced at the same time, each one costs a few JSON-RPC requests.
+const PRICING_CONCURRENCY: usize = 16;
+/// Queued pool updates reported as a pricing backlog.
+const BACKLOG_WARNING_THRESHOLD: usize = 1_000;
+
+/// Which pools of the program-wide subscription are persisted.
+#[derive(Clone, Debug, Default)]
+pub struct PoolCriteria {
+    mints: HashSet<Pubkey>,
+    min_quote_liquidity: Option<BigDecimal>,
+}
+
+impl From<&AllPools> for PoolCriteria {
+    fn from(config: &AllPools) -> Self {
+        Self {
+            mints: config.mint_allowlist.iter().copied().collect(),
+            min_quote_liquidity: config.min_quote_liquidity.clone(),
+        }
+    }
+}
+
+impl PoolCriteria {
+    fn matches(&self, pool: &LivePool) -> bool {
+        let mints = [pool.coin_mint, pool.pc_mint];
+        let listed = self.mints.is_empty() || mints.iter().any(|mint| self.mints.contains(mint));
+        let liquid = self
+            .min_quote_liquidity
+            .as_ref()
+            .map_or(true, |min| pool.quote_liquidity >= *min);
+
+        listed && liquid
+    }
+}
+
+/// Latest price of a pool, as of the newest update of its AMM account.
+#[derive(Clone, Debug)]
+pub struct LivePool {
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub price: Price,
+    /// Quote token the pool holds, in UI units.
+    pub quote_liquidity: BigDecimal,
+    pub slot: Slot,
+    write_version: u64,
+}
+
+/// Prices every Raydium AMM v4 pool streamed by the program filter and keeps the prices in memory.
+///
+/// Updates are queued and only the latest pending one of each pool is priced, so a busy pool can't hold back others.
+/// Pricing reads the vaults and market of the pool through JSON-RPC, the rate limiter keeps it within budget and
+/// updates queue up meanwhile.
+pub struct ProgramPools {
+    client: Arc<JsonRpcAccountReceiverClient>,
+    metadata_cache: PoolMetadataCache,
+    criteria: PoolCriteria,
+    rate_limiter: Option<RateLimiter>,
+    pools: RwLock<HashMap<Pubkey, LivePool>>,
+    pending: Mutex<HashMap<Pubkey, AccountUpdate>>,
+    pending_added: Notify,
+}
+
+impl ProgramPools {
+    /// Vault reads have to be fresh, so `client` must not be a cache of streamed accounts only.
+    pub fn new(
+        client: Arc<JsonRpcAccountReceiverClient>,
+        criteria: PoolCriteria,
+        pricings_per_second: Option<u32>,
+    ) -> Self {
+        Self {
+            client,
+            metadata_cache: PoolMetadataCache::default(),
+            criteria,
+            rate_limiter: pricings_per_second.map(RateLimiter::new),
+            pools: RwLock::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            pending_added: Notify::new(),
+        }
+    }
+
+    pub fn filter() -> ProgramFilter {
+        ProgramFilter { owner: RAYDIUM_AMM_V4_PROGRAM_ID, data_size: size_of::<AmmInfo>() as u64 }
+    }
+
+    pub fn accepts(&self, update: &AccountUpdate) -> bool {
+        update.owner == RAYDIUM_AMM_V4_PROGRAM_ID && update.data.len() == size_of::<AmmInfo>()
+    }
+
+    pub fn enqueue(&self, update: AccountUpdate) {
+        let mut pending = self.pending.lock().expect("pending pool updates are poisoned");
+        let queued = pending.get(&update.pubkey);
+        if queued.map_or(true, |queued| update.is_newer_than(queued.slot, queued.write_version)) {
+            pending.insert(update.pubkey, update);
+            self.pending_added.notify_one();
+        }
+    }
+
+    /// Prices the queued updates as they come and persists the pools meeting the criteria, except for the `skipped`
+    /// ones.
+    pub async fn run(self: Arc<Self>, storage: Arc<dyn PoolPriceStorage + Sync + Send>, skipped: HashSet<Pubkey>) {
+        loop {
+            self.pending_added.notified().await;
+            self.price_pending(&storage, &skipped).await;
+        }
+    }
+
+    /// Prices the updates queued so far, see [`ProgramPools::run`].
+    pub async fn price_pending(&self, storage: &Arc<dyn PoolPriceStorage + Sync + Send>, skipped: &HashSet<Pubkey>) {
+        let updates = take(&mut *self.pending.lock().expect("pending pool updates are poisoned"));
+        if updates.is_empty() {
+            return;
+        }
+        if updates.len() >= BACKLOG_WARNING_THRESHOLD {
+            warn!("Pricing a backlog of {backlog} pool updates", backlog = updates.len());
+        } else {
+            debug!("Pricing {count} pool updates", count = updates.len());
+        }
+
+        iter(updates.into_values())
+            .for_each_concurrent(PRICING_CONCURRENCY, |update| async move {
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.acquire().await;
+                }
+
+                let pool_pubkey = update.pubkey;
+                let Some(price) = self.reprice(update).await else {
+                    return;
+                };
+                if skipped.contains(&pool_pubkey) {
+                    return;
+                }
+
+                storage
+                    .save(&price)
+                    .await
+                    .inspect_err(|e| error!("Failed to save price for '{pool_pubkey}'. Cause: {e:?}"))
+                    .ok();
+            })
+            .await;
+    }
+
+    /// Updates the price of the pool, returns it when the pool has to be persisted.
+    async fn reprice(&self, update: AccountUpdate) -> Option<PoolAndPrice> {
+        let pool = self
+            .price(&update)
+            .await
+            .inspect_err(|e| debug!("Failed to price pool '{pubkey}': {e}", pubkey = update.pubkey))
+            .ok()?;
+
+        let mut pools = self.pools.write().await;
+        if let Some(known) = pools.get(&update.pubkey) {
+            if !update.is_newer_than(known.slot, known.write_version) {
+                return None;
+            }
+        }
+
+        let persisted = self.criteria.matches(&pool);
+        let price = PoolAndPrice::new(update.pubkey, pool.price.clone(), pool.slot, Utc::now().naive_utc());
+        pools.insert(update.pubkey, pool);
+        persisted.then_some(price)
+    }
+
+    async fn price(&self, update: &AccountUpdate) -> anyhow::Result<LivePool> {
+        let pool_state = load_pool_state(
+            self.client.clone(),
+            &self.metadata_cache,
+            &update.owner,
+            &update.data,
+            &RAYDIUM_AMM_V4_PROGRAM_ID,
+            &update.pubkey,
+            update.slot,
+        )
+        .await?;
+
+        let quote_liquidity = BigDecimal::new(
+            BigInt::from(pool_state.pool.pool_pc_vault_amount),
+            pool_state.pool.pool_pc_decimals as i64,
+        );
+        Ok(LivePool {
+            coin_mint: pool_state.pool_amm_keys.amm_coin_mint,
+            pc_mint: pool_state.pool_amm_keys.amm_pc_mint,
+            price: calc_coin_in_pc(&pool_state.pool)?,
+            quote_liquidity,
+            slot: update.slot,
+            write_version: update.write_version,
+        })
+    }
+
+    pub async fn current(&self, pool_pubkey: &Pubkey) -> Option<LivePool> {
+        self.pools.read().await.get(pool_pubkey).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm_math::test_pool::{TestPool, NO_ORDERBOOK_STATUS, ORDERBOOK_STATUS};
+    use std::str::FromStr;
+
+    fn pool_update(pool: &TestPool, slot: Slot) -> AccountUpdate {
+        AccountUpdate {
+            pubkey: pool.pool,
+            owner: RAYDIUM_AMM_V4_PROGRAM_ID,
+            data: pool.amm_info_data(),
+            slot,
+            write_version: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn persists_pools_with_allowlisted_mints() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let criteria = PoolCriteria { mints: HashSet::from([pool.amm_info.pc_vault_mint]), ..Default::default() };
+        let program_pools = ProgramPools::new(pool.accounts.clone(), criteria, None);
+
+        let persisted = program_pools.reprice(pool_update(&pool, 100)).await.unwrap();
+        assert_eq!((persisted.pool_pubkey, persisted.price), (pool.pool, Price::from_str("150").unwrap()));
+
+        let other_pool = TestPool::new(ORDERBOOK_STATUS);
+        let other_program_pools = ProgramPools::new(other_pool.accounts.clone(), program_pools.criteria.clone(), None);
+        assert!(other_program_pools
+            .reprice(pool_update(&other_pool, 100))
+            .await
+            .is_none());
+        assert_eq!(other_program_pools.current(&other_pool.pool).await.unwrap().price, Price::from_str("150").unwrap());
+    }
+
+    #[tokio::test]
+    async fn keeps_illiquid_pools_in_memory_only() {
+        let pool = TestPool::new(NO_ORDERBOOK_STATUS);
+        let criteria = PoolCriteria { min_quote_liquidity: Some(BigDecimal::from(140_001)), ..Default::default() };
+        let program_pools = ProgramPools::new(pool.accounts.clone(), criteria, None);
+
+        assert!(program_pools.reprice(pool_update(&pool, 100)).await.is_none());
+        assert_eq!(program_pools.current(&pool.pool).await.unwrap().quote_liquidity, BigDecimal::from(140_000));
+    }
+
+    #[tokio::test]
+    async fn ignores_stale_updates() {
+        let pool = TestPool::new(ORDERBOOK_STATUS);
+        let program_pools = ProgramPools::new(pool.accounts.clone(), PoolCriteria::default(), None);
+
+        assert!(program_pools.reprice(pool_update(&pool, 100)).await.is_some());
+        assert!(program_pools.reprice(pool_update(&pool, 90)).await.is_none());
+        assert_eq!(program_pools.current(&pool.pool).await.unwrap().slot, 100);
+    }
+}
@@ -0,0 +1,67 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// Which pools have to be repriced when one of the streamed accounts changes.
+#[derive(Default)]
+pub struct TrackedPools {
+    accounts_by_pool: HashMap<Pubkey, Vec<Pubkey>>,
+    pools_by_account: HashMap<Pubkey, Vec<Pubkey>>,
+}
+
+impl TrackedPools {
+    /// Tracks `accounts` for the pool instead of the ones it had before, returns whether anything changed.
+    pub fn track(&mut self, pool_pubkey: Pubkey, accounts: Vec<Pubkey>) -> bool {
+        if self.accounts_by_pool.get(&pool_pubkey) == Some(&accounts) {
+            return false;
+        }
+
+        self.untrack(&pool_pubkey);
+        for account in &accounts {
+            self.pools_by_account.entry(*account).or_default().push(pool_pubkey);
+        }
+        self.accounts_by_pool.insert(pool_pubkey, accounts);
+        true
+    }
+
+    pub fn untrack(&mut self, pool_pubkey: &Pubkey) {
+        for account in self.accounts_by_pool.remove(pool_pubkey).into_iter().flatten() {
+            if let Some(pools) = self.pools_by_account.get_mut(&account) {
+                pools.retain(|pool| pool != pool_pubkey);
+                if pools.is_empty() {
+                    self.pools_by_account.remove(&account);
+                }
+            }
+        }
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = &Pubkey> {
+        self.accounts_by_pool.keys()
+    }
+
+    pub fn accounts_of(&self, pool_pubkey: &Pubkey) -> Vec<Pubkey> {
+        self.accounts_by_pool.get(pool_pubkey).cloned().unwrap_or_default()
+    }
+
+    pub fn pools_of(&self, account: &Pubkey) -> Vec<Pubkey> {
+        self.pools_by_account.get(account).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retracks_changed_dependencies() {
+        let (pool, old_market, new_market) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut tracked = TrackedPools::default();
+
+        assert!(tracked.track(pool, vec![pool, old_market]));
+        assert!(!tracked.track(pool, vec![pool, old_market]));
+        assert!(tracked.track(pool, vec![pool, new_market]));
+
+        assert_eq!(tracked.pools_of(&pool), vec![pool]);
+        assert_eq!(tracked.pools_of(&new_market), vec![pool]);
+        assert!(tracked.pools_of(&old_market).is_empty());
+    }
+}
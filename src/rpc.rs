@@ -1,15 +1,87 @@
 use async_trait::async_trait;
 use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 
+pub mod account_cache;
+pub mod capture;
+pub mod endpoint_race;
+#[cfg(test)]
+pub mod in_memory;
 mod json_rpc;
+pub mod multiplexer;
+pub mod polling;
+pub mod resilient;
+pub mod subscription;
+pub mod supervisor;
+pub mod websocket;
 pub mod yellowstone_grpc;
 
+#[derive(Clone, Debug)]
+pub struct AccountUpdate {
+    pub pubkey: Pubkey,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+    pub slot: Slot,
+    /// Orders updates of the same account within a slot.
+    pub write_version: u64,
+}
+
+impl AccountUpdate {
+    pub fn is_newer_than(&self, slot: Slot, write_version: u64) -> bool {
+        (self.slot, self.write_version) > (slot, write_version)
+    }
+}
+
+/// Instruction of a transaction with its program and accounts resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutedInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TransactionUpdate {
+    pub signature: Signature,
+    pub slot: Slot,
+    /// Static account keys followed by the ones loaded from lookup tables, the fee payer comes first.
+    pub account_keys: Vec<Pubkey>,
+    /// Top-level instructions, each one followed by the instructions it invoked, in execution order.
+    pub instructions: Vec<ExecutedInstruction>,
+    pub log_messages: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum StreamEvent {
+    Account(AccountUpdate),
+    /// Successful transaction matching a transaction filter.
+    Transaction(TransactionUpdate),
+    /// The stream was subscribed again after a disconnect, updates after `last_slot` may have been missed.
+    Resubscribed {
+        stream_name: String,
+        last_slot: Option<Slot>,
+    },
+}
+
 #[async_trait]
 pub trait JsonRpcAccountReceiver: Send + Sync {
-    async fn get_account(&self, pubkey: &Pubkey) -> anyhow::Result<Account>;
+    /// `min_context_slot` makes the node refuse to answer with a state older than the given slot.
+    async fn get_account(&self, pubkey: &Pubkey, min_context_slot: Option<Slot>) -> anyhow::Result<Account>;
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>>;
 
-    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> anyhow::Result<Vec<Account>>;
+    /// Same as `get_multiple_accounts`, but always asks the node and returns the slot the accounts were read at.
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)>;
 
     async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>>;
 }
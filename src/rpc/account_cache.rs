@@ -0,0 +1,286 @@
+use crate::rpc::{AccountUpdate, JsonRpcAccountReceiver};
+use anyhow::{ensure, Context};
+use async_trait::async_trait;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How long an account read through the fallback is served before it is read again, streamed accounts never expire.
+const FETCHED_ACCOUNT_TTL: Duration = Duration::from_secs(30);
+
+struct CachedAccount {
+    account: Account,
+    slot: Slot,
+    write_version: u64,
+    /// When the account was read through the fallback, `None` once it was streamed.
+    fetched_at: Option<Instant>,
+}
+
+impl CachedAccount {
+    /// Streamed accounts are known to be unchanged up to the latest streamed slot, fetched ones only at their slot.
+    fn is_usable(&self, min_context_slot: Option<Slot>, streamed_slot: Slot) -> bool {
+        let known_slot = match self.fetched_at {
+            Some(fetched_at) if fetched_at.elapsed() > FETCHED_ACCOUNT_TTL => return false,
+            Some(_) => self.slot,
+            None => self.slot.max(streamed_slot),
+        };
+        known_slot >= min_context_slot.unwrap_or_default()
+    }
+}
+
+/// Latest known state of the streamed accounts.
+///
+/// Reads are served from memory when the cached state is known at the requested slot, other accounts are fetched
+/// through the fallback receiver and kept for a short while.
+pub struct AccountStateCache {
+    accounts: RwLock<HashMap<Pubkey, CachedAccount>>,
+    /// Slot of the latest update streamed for any account.
+    streamed_slot: AtomicU64,
+    fallback: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+}
+
+impl AccountStateCache {
+    pub fn new(fallback: Arc<dyn JsonRpcAccountReceiver + Send + Sync>) -> Self {
+        Self {
+            accounts: RwLock::new(HashMap::new()),
+            streamed_slot: AtomicU64::new(0),
+            fallback,
+        }
+    }
+
+    /// Applies the update unless a newer state of the account is already known, returns whether it was applied.
+    pub async fn update(&self, update: &AccountUpdate) -> bool {
+        let mut accounts = self.accounts.write().await;
+        self.streamed_slot.fetch_max(update.slot, Ordering::SeqCst);
+        if let Some(cached) = accounts.get(&update.pubkey) {
+            if !update.is_newer_than(cached.slot, cached.write_version) {
+                return false;
+            }
+        }
+
+        let account = Account {
+            lamports: 0,
+            data: update.data.clone(),
+            owner: update.owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        accounts.insert(
+            update.pubkey,
+            CachedAccount {
+                account,
+                slot: update.slot,
+                write_version: update.write_version,
+                fetched_at: None,
+            },
+        );
+        true
+    }
+
+    async fn cache_fetched(&self, pubkeys: &[Pubkey], fetched_accounts: Vec<Account>, slot: Slot) {
+        let mut accounts = self.accounts.write().await;
+        for (pubkey, account) in pubkeys.iter().zip(fetched_accounts) {
+            // A streamed update may have arrived while the fallback request was in flight, the newer state wins.
+            let keep_cached = accounts
+                .get(pubkey)
+                .is_some_and(|cached| cached.fetched_at.is_none() && cached.slot >= slot);
+            if !keep_cached {
+                accounts.insert(
+                    *pubkey,
+                    CachedAccount { account, slot, write_version: 0, fetched_at: Some(Instant::now()) },
+                );
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcAccountReceiver for AccountStateCache {
+    async fn get_account(&self, pubkey: &Pubkey, min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+        let mut accounts = self.get_multiple_accounts(&[*pubkey], min_context_slot).await?;
+        accounts.pop().with_context(|| format!("Account '{pubkey}' is missing"))
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>> {
+        let missing_pubkeys = {
+            let accounts = self.accounts.read().await;
+            let streamed_slot = self.streamed_slot.load(Ordering::SeqCst);
+            pubkeys
+                .iter()
+                .filter(|pubkey| {
+                    !accounts
+                        .get(*pubkey)
+                        .is_some_and(|cached| cached.is_usable(min_context_slot, streamed_slot))
+                })
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        if !missing_pubkeys.is_empty() {
+            let (slot, fetched_accounts) = self
+                .fallback
+                .get_multiple_accounts_with_slot(&missing_pubkeys, min_context_slot)
+                .await?;
+            ensure!(
+                fetched_accounts.len() == missing_pubkeys.len(),
+                "Requested {} accounts, received {}",
+                missing_pubkeys.len(),
+                fetched_accounts.len()
+            );
+            self.cache_fetched(&missing_pubkeys, fetched_accounts, slot).await;
+        }
+
+        let accounts = self.accounts.read().await;
+        pubkeys
+            .iter()
+            .map(|pubkey| {
+                accounts
+                    .get(pubkey)
+                    .map(|cached| cached.account.clone())
+                    .with_context(|| format!("Account '{pubkey}' is missing"))
+            })
+            .collect()
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)> {
+        self.fallback
+            .get_multiple_accounts_with_slot(pubkeys, min_context_slot)
+            .await
+    }
+
+    async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        Ok(self.get_account(pubkey, None).await?.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Default)]
+    struct CountingReceiver {
+        requested_accounts: AtomicUsize,
+        context_slot: Slot,
+    }
+
+    #[async_trait]
+    impl JsonRpcAccountReceiver for CountingReceiver {
+        async fn get_account(&self, _pubkey: &Pubkey, _min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+            self.requested_accounts.fetch_add(1, Ordering::SeqCst);
+            Ok(Account { data: vec![1], ..Account::default() })
+        }
+
+        async fn get_multiple_accounts(
+            &self,
+            pubkeys: &[Pubkey],
+            _min_context_slot: Option<Slot>,
+        ) -> anyhow::Result<Vec<Account>> {
+            self.requested_accounts.fetch_add(pubkeys.len(), Ordering::SeqCst);
+            Ok(pubkeys
+                .iter()
+                .map(|_| Account { data: vec![1], ..Account::default() })
+                .collect())
+        }
+
+        async fn get_multiple_accounts_with_slot(
+            &self,
+            pubkeys: &[Pubkey],
+            min_context_slot: Option<Slot>,
+        ) -> anyhow::Result<(Slot, Vec<Account>)> {
+            let slot = min_context_slot.unwrap_or_default().max(self.context_slot);
+            Ok((slot, self.get_multiple_accounts(pubkeys, min_context_slot).await?))
+        }
+
+        async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
+            Ok(self.get_account(pubkey, None).await?.data)
+        }
+    }
+
+    fn account_update(pubkey: Pubkey, data: Vec<u8>, slot: Slot, write_version: u64) -> AccountUpdate {
+        AccountUpdate { pubkey, owner: Pubkey::default(), data, slot, write_version }
+    }
+
+    #[tokio::test]
+    async fn serves_streamed_accounts_from_memory() {
+        let fallback = Arc::new(CountingReceiver::default());
+        let cache = AccountStateCache::new(fallback.clone());
+        let (streamed, unknown) = (Pubkey::new_unique(), Pubkey::new_unique());
+        cache.update(&account_update(streamed, vec![2], 10, 0)).await;
+
+        let accounts = cache
+            .get_multiple_accounts(&[streamed, unknown], Some(10))
+            .await
+            .unwrap();
+        cache.get_account(&unknown, Some(10)).await.unwrap();
+
+        assert_eq!(accounts[0].data, vec![2]);
+        assert_eq!(accounts[1].data, vec![1]);
+        assert_eq!(fallback.requested_accounts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn ignores_stale_updates() {
+        let cache = AccountStateCache::new(Arc::new(CountingReceiver::default()));
+        let pubkey = Pubkey::new_unique();
+
+        assert!(cache.update(&account_update(pubkey, vec![1], 10, 5)).await);
+        assert!(!cache.update(&account_update(pubkey, vec![2], 10, 4)).await);
+        assert!(!cache.update(&account_update(pubkey, vec![3], 9, 7)).await);
+        assert!(cache.update(&account_update(pubkey, vec![4], 11, 0)).await);
+
+        assert_eq!(cache.get_account(&pubkey, None).await.unwrap().data, vec![4]);
+    }
+
+    #[tokio::test]
+    async fn refetches_accounts_older_than_the_requested_slot() {
+        let fallback = Arc::new(CountingReceiver::default());
+        let cache = AccountStateCache::new(fallback.clone());
+        let pubkey = Pubkey::new_unique();
+        cache.update(&account_update(pubkey, vec![2], 10, 0)).await;
+
+        assert_eq!(cache.get_account(&pubkey, Some(10)).await.unwrap().data, vec![2]);
+        assert_eq!(cache.get_account(&pubkey, Some(11)).await.unwrap().data, vec![1]);
+        assert_eq!(fallback.requested_accounts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn serves_accounts_unchanged_up_to_the_latest_streamed_slot() {
+        let fallback = Arc::new(CountingReceiver::default());
+        let cache = AccountStateCache::new(fallback.clone());
+        let (quiet, active) = (Pubkey::new_unique(), Pubkey::new_unique());
+        cache.update(&account_update(quiet, vec![2], 10, 0)).await;
+        cache.update(&account_update(active, vec![3], 12, 0)).await;
+
+        assert_eq!(cache.get_account(&quiet, Some(12)).await.unwrap().data, vec![2]);
+        assert_eq!(fallback.requested_accounts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_fetched_accounts_at_the_slot_they_were_read() {
+        let fallback = Arc::new(CountingReceiver { context_slot: 20, ..CountingReceiver::default() });
+        let cache = AccountStateCache::new(fallback.clone());
+        let pubkey = Pubkey::new_unique();
+
+        cache.get_account(&pubkey, None).await.unwrap();
+        cache.get_account(&pubkey, Some(20)).await.unwrap();
+        cache.get_account(&pubkey, Some(21)).await.unwrap();
+
+        assert_eq!(fallback.requested_accounts.load(Ordering::SeqCst), 2);
+        assert!(!cache.update(&account_update(pubkey, vec![2], 19, 3)).await);
+        assert!(cache.update(&account_update(pubkey, vec![2], 21, 3)).await);
+    }
+}
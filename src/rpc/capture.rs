@@ -0,0 +1,499 @@
+use crate::rpc::{AccountUpdate, ExecutedInstruction, JsonRpcAccountReceiver, StreamEvent, TransactionUpdate};
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::fs::{read_to_string, File};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::warn;
+
+/// A line of a capture file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureRecord {
+    /// Account update received from a stream.
+    Update {
+        #[serde(with = "display_string")]
+        pubkey: Pubkey,
+        #[serde(with = "display_string")]
+        owner: Pubkey,
+        slot: Slot,
+        write_version: u64,
+        #[serde(with = "base64_data")]
+        data: Vec<u8>,
+    },
+    /// Transaction received from a stream.
+    Transaction {
+        #[serde(with = "display_string")]
+        signature: Signature,
+        slot: Slot,
+        #[serde(with = "display_strings")]
+        account_keys: Vec<Pubkey>,
+        instructions: Vec<CapturedInstruction>,
+        log_messages: Vec<String>,
+    },
+    Resubscribed {
+        stream_name: String,
+        last_slot: Option<Slot>,
+    },
+    /// Account returned by a JSON-RPC request, the slot is only known for `get_multiple_accounts_with_slot`.
+    RpcAccount {
+        #[serde(default)]
+        consumer: RpcConsumer,
+        #[serde(with = "display_string")]
+        pubkey: Pubkey,
+        slot: Option<Slot>,
+        #[serde(with = "display_string")]
+        owner: Pubkey,
+        lamports: u64,
+        #[serde(with = "base64_data")]
+        data: Vec<u8>,
+    },
+}
+
+/// Component a JSON-RPC response was read by.
+///
+/// Components read concurrently, so the order of their responses in a capture is arbitrary. A replay answers each one
+/// from its own responses only.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcConsumer {
+    /// Pricing of the listed and detected pools, resynchronizations included.
+    #[default]
+    TrackedPools,
+    /// Pricing of the pools streamed by the program-wide subscription.
+    ProgramPools,
+    /// Quotes and order books asked for through the bot.
+    Queries,
+    /// Polling of the accounts of disconnected streams.
+    Polling,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CapturedInstruction {
+    #[serde(with = "display_string")]
+    program_id: Pubkey,
+    #[serde(with = "display_strings")]
+    accounts: Vec<Pubkey>,
+    #[serde(with = "base64_data")]
+    data: Vec<u8>,
+}
+
+impl From<&ExecutedInstruction> for CapturedInstruction {
+    fn from(instruction: &ExecutedInstruction) -> Self {
+        Self {
+            program_id: instruction.program_id,
+            accounts: instruction.accounts.clone(),
+            data: instruction.data.clone(),
+        }
+    }
+}
+
+impl From<&CapturedInstruction> for ExecutedInstruction {
+    fn from(instruction: &CapturedInstruction) -> Self {
+        Self {
+            program_id: instruction.program_id,
+            accounts: instruction.accounts.clone(),
+            data: instruction.data.clone(),
+        }
+    }
+}
+
+impl CaptureRecord {
+    fn rpc_account(consumer: RpcConsumer, pubkey: Pubkey, slot: Option<Slot>, account: &Account) -> Self {
+        Self::RpcAccount {
+            consumer,
+            pubkey,
+            slot,
+            owner: account.owner,
+            lamports: account.lamports,
+            data: account.data.clone(),
+        }
+    }
+
+    pub fn from_event(event: &StreamEvent) -> Self {
+        match event {
+            StreamEvent::Account(update) => Self::Update {
+                pubkey: update.pubkey,
+                owner: update.owner,
+                slot: update.slot,
+                write_version: update.write_version,
+                data: update.data.clone(),
+            },
+            StreamEvent::Transaction(transaction) => Self::Transaction {
+                signature: transaction.signature,
+                slot: transaction.slot,
+                account_keys: transaction.account_keys.clone(),
+                instructions: transaction.instructions.iter().map(CapturedInstruction::from).collect(),
+                log_messages: transaction.log_messages.clone(),
+            },
+            StreamEvent::Resubscribed { stream_name, last_slot } => {
+                Self::Resubscribed { stream_name: stream_name.clone(), last_slot: *last_slot }
+            }
+        }
+    }
+}
+
+/// Pubkeys and signatures as their base58 strings.
+mod display_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        T::from_str(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+mod display_strings {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(ToString::to_string))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|value| T::from_str(value).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+mod base64_data {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Appends records to a JSONL capture file, every record is flushed right away.
+#[derive(Clone)]
+pub struct CaptureWriter {
+    file: Arc<tokio::sync::Mutex<BufWriter<File>>>,
+}
+
+impl CaptureWriter {
+    pub async fn create(path: &str) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .await
+            .with_context(|| format!("Failed to create capture file '{path}'"))?;
+
+        Ok(Self { file: Arc::new(tokio::sync::Mutex::new(BufWriter::new(file))) })
+    }
+
+    pub async fn record(&self, record: &CaptureRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Records every account the wrapped receiver returns to `consumer`.
+pub struct RecordingAccountReceiver {
+    inner: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+    writer: CaptureWriter,
+    consumer: RpcConsumer,
+}
+
+impl RecordingAccountReceiver {
+    pub fn new(
+        inner: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+        writer: CaptureWriter,
+        consumer: RpcConsumer,
+    ) -> Self {
+        Self { inner, writer, consumer }
+    }
+
+    async fn record(&self, pubkeys: &[Pubkey], accounts: &[Account], slot: Option<Slot>) {
+        for (pubkey, account) in pubkeys.iter().zip(accounts) {
+            self.writer
+                .record(&CaptureRecord::rpc_account(self.consumer, *pubkey, slot, account))
+                .await
+                .inspect_err(|e| warn!("Failed to record account '{pubkey}': {e}"))
+                .ok();
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcAccountReceiver for RecordingAccountReceiver {
+    async fn get_account(&self, pubkey: &Pubkey, min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+        let account = self.inner.get_account(pubkey, min_context_slot).await?;
+        self.record(&[*pubkey], &[account.clone()], None).await;
+        Ok(account)
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>> {
+        let accounts = self.inner.get_multiple_accounts(pubkeys, min_context_slot).await?;
+        self.record(pubkeys, &accounts, None).await;
+        Ok(accounts)
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)> {
+        let (slot, accounts) = self
+            .inner
+            .get_multiple_accounts_with_slot(pubkeys, min_context_slot)
+            .await?;
+        self.record(pubkeys, &accounts, Some(slot)).await;
+        Ok((slot, accounts))
+    }
+
+    /// Reads the whole account, a replay may be asked for more than the data.
+    async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        Ok(self.get_account(pubkey, None).await?.data)
+    }
+}
+
+/// Records of a capture file, in the order they were written.
+#[derive(Clone, Debug, Default)]
+pub struct Capture {
+    pub records: Vec<CaptureRecord>,
+}
+
+impl Capture {
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let content = read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read capture file '{path}'"))?;
+        let records = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let line_number = index + 1;
+                serde_json::from_str(line).with_context(|| format!("Malformed record on line {line_number} of {path}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { records })
+    }
+
+    /// Stream events in the order they were received.
+    pub fn events(&self) -> Vec<StreamEvent> {
+        self.records
+            .iter()
+            .filter_map(|record| match record {
+                CaptureRecord::Update { pubkey, owner, slot, write_version, data } => {
+                    Some(StreamEvent::Account(AccountUpdate {
+                        pubkey: *pubkey,
+                        owner: *owner,
+                        data: data.clone(),
+                        slot: *slot,
+                        write_version: *write_version,
+                    }))
+                }
+                CaptureRecord::Transaction { signature, slot, account_keys, instructions, log_messages } => {
+                    Some(StreamEvent::Transaction(TransactionUpdate {
+                        signature: *signature,
+                        slot: *slot,
+                        account_keys: account_keys.clone(),
+                        instructions: instructions.iter().map(ExecutedInstruction::from).collect(),
+                        log_messages: log_messages.clone(),
+                    }))
+                }
+                CaptureRecord::Resubscribed { stream_name, last_slot } => {
+                    Some(StreamEvent::Resubscribed { stream_name: stream_name.clone(), last_slot: *last_slot })
+                }
+                CaptureRecord::RpcAccount { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Answers the JSON-RPC requests of `consumer` with the responses recorded for it.
+    pub fn account_receiver(&self, consumer: RpcConsumer) -> ReplayAccountReceiver {
+        let mut responses = HashMap::<Pubkey, VecDeque<(Option<Slot>, Account)>>::new();
+        for record in &self.records {
+            if let CaptureRecord::RpcAccount { consumer: recorded_by, pubkey, slot, owner, lamports, data } = record {
+                if *recorded_by != consumer {
+                    continue;
+                }
+
+                let account = Account { lamports: *lamports, data: data.clone(), owner: *owner, ..Account::default() };
+                responses.entry(*pubkey).or_default().push_back((*slot, account));
+            }
+        }
+
+        ReplayAccountReceiver { responses: Mutex::new(responses) }
+    }
+}
+
+/// Answers JSON-RPC requests from a capture without any network access.
+///
+/// Responses of every account are served in the order they were recorded, the last one is repeated once the others
+/// are used up.
+pub struct ReplayAccountReceiver {
+    responses: Mutex<HashMap<Pubkey, VecDeque<(Option<Slot>, Account)>>>,
+}
+
+impl ReplayAccountReceiver {
+    fn next_response(&self, pubkey: &Pubkey) -> anyhow::Result<(Option<Slot>, Account)> {
+        let mut responses = self.responses.lock().expect("replayed responses are poisoned");
+        let account_responses = responses
+            .get_mut(pubkey)
+            .with_context(|| format!("no recorded response for account '{pubkey}'"))?;
+
+        match account_responses.len() {
+            1 => Ok(account_responses[0].clone()),
+            _ => account_responses.pop_front().context("no recorded response"),
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcAccountReceiver for ReplayAccountReceiver {
+    async fn get_account(&self, pubkey: &Pubkey, _min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+        Ok(self.next_response(pubkey)?.1)
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        _min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>> {
+        pubkeys.iter().map(|pubkey| Ok(self.next_response(pubkey)?.1)).collect()
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)> {
+        let responses = pubkeys
+            .iter()
+            .map(|pubkey| self.next_response(pubkey))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let slot = responses
+            .iter()
+            .filter_map(|(slot, _)| *slot)
+            .max()
+            .or(min_context_slot)
+            .unwrap_or_default();
+
+        Ok((slot, responses.into_iter().map(|(_, account)| account).collect()))
+    }
+
+    async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        Ok(self.next_response(pubkey)?.1.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+        Account { lamports: 1, data, owner, ..Account::default() }
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_events_and_responses() {
+        let path = std::env::temp_dir().join(format!("peanut-capture-{}.jsonl", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        let (pubkey, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let update = AccountUpdate { pubkey, owner, data: vec![1, 2], slot: 7, write_version: 3 };
+
+        let transaction = TransactionUpdate {
+            signature: Signature::new_unique(),
+            slot: 8,
+            account_keys: vec![owner, pubkey],
+            instructions: vec![ExecutedInstruction { program_id: owner, accounts: vec![pubkey], data: vec![9] }],
+            log_messages: vec!["Program log: swap".to_string()],
+        };
+
+        let writer = CaptureWriter::create(path).await.unwrap();
+        writer
+            .record(&CaptureRecord::from_event(&StreamEvent::Account(update)))
+            .await
+            .unwrap();
+        writer
+            .record(&CaptureRecord::from_event(&StreamEvent::Transaction(transaction.clone())))
+            .await
+            .unwrap();
+        let recorded = Capture {
+            records: vec![
+                CaptureRecord::rpc_account(RpcConsumer::TrackedPools, pubkey, None, &account(owner, vec![1])),
+                CaptureRecord::rpc_account(RpcConsumer::TrackedPools, pubkey, Some(9), &account(owner, vec![2])),
+            ],
+        };
+        let inner = Arc::new(recorded.account_receiver(RpcConsumer::TrackedPools));
+        let tracked_pools = RecordingAccountReceiver::new(inner.clone(), writer.clone(), RpcConsumer::TrackedPools);
+        let program_pools = RecordingAccountReceiver::new(inner, writer, RpcConsumer::ProgramPools);
+        tracked_pools.get_account(&pubkey, None).await.unwrap();
+        program_pools.get_account(&pubkey, None).await.unwrap();
+        tracked_pools
+            .get_multiple_accounts_with_slot(&[pubkey], None)
+            .await
+            .unwrap();
+
+        let capture = Capture::load(path).await.unwrap();
+        std::fs::remove_file(path).ok();
+
+        let events = capture.events();
+        let [StreamEvent::Account(replayed_update), StreamEvent::Transaction(replayed_transaction)] = events.as_slice()
+        else {
+            panic!("expected an account update and a transaction");
+        };
+        assert_eq!((replayed_update.pubkey, replayed_update.slot, replayed_update.write_version), (pubkey, 7, 3));
+        assert_eq!(replayed_update.data, vec![1, 2]);
+        assert_eq!((replayed_transaction.signature, replayed_transaction.slot), (transaction.signature, 8));
+        assert_eq!(replayed_transaction.account_keys, transaction.account_keys);
+        assert_eq!(replayed_transaction.instructions, transaction.instructions);
+        assert_eq!(replayed_transaction.log_messages, transaction.log_messages);
+
+        let replay = capture.account_receiver(RpcConsumer::TrackedPools);
+        assert_eq!(replay.get_account(&pubkey, None).await.unwrap().data, vec![1]);
+        assert_eq!(replay.get_multiple_accounts_with_slot(&[pubkey], None).await.unwrap().0, 9);
+        assert_eq!(replay.get_account_data(&pubkey).await.unwrap(), vec![2]);
+        let replay = capture.account_receiver(RpcConsumer::ProgramPools);
+        let program_pools_response = replay.get_multiple_accounts_with_slot(&[pubkey], None).await.unwrap();
+        assert_eq!(program_pools_response, (0, vec![account(owner, vec![2])]));
+        assert!(capture
+            .account_receiver(RpcConsumer::Queries)
+            .get_account(&pubkey, None)
+            .await
+            .is_err());
+    }
+}
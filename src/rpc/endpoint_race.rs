@@ -0,0 +1,193 @@
+use crate::rpc::supervisor::{ConnectionState, ConnectionStatuses};
+use crate::rpc::{AccountUpdate, StreamEvent, TransactionUpdate};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::interval;
+use tracing::info;
+
+/// Updates older than this many slots behind the newest one are forgotten, late duplicates of them pass through.
+const SEEN_UPDATES_SLOTS: Slot = 150;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum UpdateKey {
+    Account(Pubkey, Slot, u64),
+    Transaction(Signature, Slot),
+}
+
+impl UpdateKey {
+    fn slot(&self) -> Slot {
+        match self {
+            UpdateKey::Account(_, slot, _) | UpdateKey::Transaction(_, slot) => *slot,
+        }
+    }
+}
+
+/// How an endpoint performs against the others streaming the same accounts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EndpointLatency {
+    pub first_arrivals: u64,
+    pub late_arrivals: u64,
+    /// Sum of the delays behind the first arrival of every late update.
+    pub total_lag: Duration,
+}
+
+impl EndpointLatency {
+    pub fn average_lag(&self) -> Duration {
+        match self.late_arrivals {
+            0 => Duration::ZERO,
+            late_arrivals => self.total_lag.div_f64(late_arrivals as f64),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RaceState {
+    first_seen: HashMap<UpdateKey, Instant>,
+    max_slot: Slot,
+    latencies: HashMap<String, EndpointLatency>,
+}
+
+/// Deduplicates updates streamed from several endpoints by `(pubkey, slot, write_version)` and transactions by
+/// signature, the first arrival wins.
+#[derive(Clone, Default)]
+pub struct EndpointRace {
+    state: Arc<Mutex<RaceState>>,
+}
+
+impl EndpointRace {
+    /// Returns whether the update arrived first and has to be passed on.
+    pub fn accept(&self, endpoint: &str, update: &AccountUpdate) -> bool {
+        self.accept_at(endpoint, update, Instant::now())
+    }
+
+    pub fn accept_transaction(&self, endpoint: &str, transaction: &TransactionUpdate) -> bool {
+        self.accept_key_at(endpoint, UpdateKey::Transaction(transaction.signature, transaction.slot), Instant::now())
+    }
+
+    fn accept_at(&self, endpoint: &str, update: &AccountUpdate, arrived_at: Instant) -> bool {
+        self.accept_key_at(endpoint, UpdateKey::Account(update.pubkey, update.slot, update.write_version), arrived_at)
+    }
+
+    fn accept_key_at(&self, endpoint: &str, key: UpdateKey, arrived_at: Instant) -> bool {
+        let mut state = self.state.lock().expect("endpoint race state is poisoned");
+        let first_seen = state.first_seen.get(&key).copied();
+        let latency = state.latencies.entry(endpoint.to_string()).or_default();
+
+        if let Some(first_seen) = first_seen {
+            latency.late_arrivals += 1;
+            latency.total_lag += arrived_at.saturating_duration_since(first_seen);
+            return false;
+        }
+
+        latency.first_arrivals += 1;
+        state.first_seen.insert(key, arrived_at);
+        if key.slot() > state.max_slot {
+            state.max_slot = key.slot();
+            let oldest_slot = state.max_slot.saturating_sub(SEEN_UPDATES_SLOTS);
+            state.first_seen.retain(|key, _| key.slot() >= oldest_slot);
+        }
+        true
+    }
+
+    pub fn latencies(&self) -> Vec<(String, EndpointLatency)> {
+        let state = self.state.lock().expect("endpoint race state is poisoned");
+        state
+            .latencies
+            .iter()
+            .map(|(endpoint, latency)| (endpoint.clone(), latency.clone()))
+            .collect()
+    }
+}
+
+/// Passes on the first arrivals among the events of one racing connection.
+///
+/// A resubscription is only passed on when no other connection of the same stream stayed up, otherwise nothing was
+/// missed.
+pub async fn forward_first_arrivals(
+    race: EndpointRace,
+    endpoint: String,
+    mut events: Receiver<StreamEvent>,
+    sender: Sender<StreamEvent>,
+    sibling_connections: Vec<String>,
+    statuses: ConnectionStatuses,
+) {
+    while let Some(event) = events.recv().await {
+        let forward = match &event {
+            StreamEvent::Account(update) => race.accept(&endpoint, update),
+            StreamEvent::Transaction(transaction) => race.accept_transaction(&endpoint, transaction),
+            StreamEvent::Resubscribed { .. } => !any_connected(&statuses, &sibling_connections).await,
+        };
+
+        if forward && sender.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn any_connected(statuses: &ConnectionStatuses, connections: &[String]) -> bool {
+    for connection in connections {
+        if let Some(ConnectionState::Connected { .. }) = statuses.get(connection).await {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub async fn report_latencies(race: EndpointRace, every: Duration) {
+    let mut report_interval = interval(every);
+
+    loop {
+        report_interval.tick().await;
+        for (endpoint, latency) in race.latencies() {
+            info!(
+                "Endpoint {endpoint} delivered {first_arrivals} updates first, \
+                 {late_arrivals} late by {lag_ms} ms on average",
+                first_arrivals = latency.first_arrivals,
+                late_arrivals = latency.late_arrivals,
+                lag_ms = latency.average_lag().as_millis(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_update(pubkey: Pubkey, slot: Slot, write_version: u64) -> AccountUpdate {
+        AccountUpdate { pubkey, owner: Pubkey::default(), data: vec![], slot, write_version }
+    }
+
+    #[test]
+    fn first_arrival_wins_and_lag_is_measured() {
+        let race = EndpointRace::default();
+        let update = account_update(Pubkey::new_unique(), 10, 1);
+        let arrived_at = Instant::now();
+
+        assert!(race.accept_at("fast", &update, arrived_at));
+        assert!(!race.accept_at("slow", &update, arrived_at + Duration::from_millis(40)));
+        assert!(race.accept_at("slow", &account_update(update.pubkey, 10, 2), arrived_at));
+
+        let latencies = race.latencies().into_iter().collect::<HashMap<_, _>>();
+        assert_eq!(latencies["fast"].first_arrivals, 1);
+        assert_eq!(latencies["slow"].first_arrivals, 1);
+        assert_eq!(latencies["slow"].average_lag(), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn forgets_old_slots() {
+        let race = EndpointRace::default();
+        let old_update = account_update(Pubkey::new_unique(), 10, 0);
+
+        race.accept("a", &old_update);
+        race.accept("a", &account_update(Pubkey::new_unique(), 10 + SEEN_UPDATES_SLOTS + 1, 0));
+
+        assert!(race.accept("b", &old_update));
+    }
+}
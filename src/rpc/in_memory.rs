@@ -0,0 +1,141 @@
+use crate::rpc::JsonRpcAccountReceiver;
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::Deserialize;
+use solana_account_decoder::UiAccount;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// An account as printed by `solana account <pubkey> --output json`.
+#[derive(Deserialize)]
+struct AccountDump {
+    pubkey: String,
+    account: UiAccount,
+}
+
+/// A fixture file holds either a single account dump or an array of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Fixture {
+    Single(AccountDump),
+    Many(Vec<AccountDump>),
+}
+
+/// Serves accounts from memory, every read is answered at the requested `min_context_slot`.
+#[derive(Default)]
+pub struct InMemoryAccountReceiver {
+    accounts: RwLock<HashMap<Pubkey, Account>>,
+}
+
+impl InMemoryAccountReceiver {
+    pub fn from_fixture(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let receiver = Self::default();
+        receiver.load_fixture(path)?;
+        Ok(receiver)
+    }
+
+    pub fn insert(&self, pubkey: Pubkey, account: Account) {
+        self.accounts
+            .write()
+            .expect("account store is poisoned")
+            .insert(pubkey, account);
+    }
+
+    /// Adds the base64 encoded account dumps of a fixture file.
+    pub fn load_fixture(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let fixture = fs::read_to_string(path).with_context(|| format!("Failed to read fixture {path:?}"))?;
+        let fixture: Fixture = serde_json::from_str(&fixture).with_context(|| format!("Malformed fixture {path:?}"))?;
+        let dumps = match fixture {
+            Fixture::Single(dump) => vec![dump],
+            Fixture::Many(dumps) => dumps,
+        };
+
+        for dump in dumps {
+            let pubkey = Pubkey::from_str(&dump.pubkey).with_context(|| format!("Malformed pubkey {}", dump.pubkey))?;
+            let account = dump
+                .account
+                .decode::<Account>()
+                .with_context(|| format!("Malformed account {pubkey}"))?;
+            self.insert(pubkey, account);
+        }
+
+        Ok(())
+    }
+
+    fn account(&self, pubkey: &Pubkey) -> anyhow::Result<Account> {
+        let accounts = self.accounts.read().expect("account store is poisoned");
+        accounts
+            .get(pubkey)
+            .cloned()
+            .with_context(|| format!("no account found for {pubkey}"))
+    }
+}
+
+#[async_trait]
+impl JsonRpcAccountReceiver for InMemoryAccountReceiver {
+    async fn get_account(&self, pubkey: &Pubkey, _min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+        self.account(pubkey)
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        _min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>> {
+        pubkeys.iter().map(|pubkey| self.account(pubkey)).collect()
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)> {
+        let accounts = self.get_multiple_accounts(pubkeys, min_context_slot).await?;
+        Ok((min_context_slot.unwrap_or_default(), accounts))
+    }
+
+    async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        Ok(self.account(pubkey)?.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::program_pack::Pack;
+    use solana_sdk::pubkey;
+
+    const USDC_TOKEN_ACCOUNT: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/accounts/usdc_token_account.json");
+
+    #[tokio::test]
+    async fn loads_account_dumps() {
+        let receiver = InMemoryAccountReceiver::from_fixture(USDC_TOKEN_ACCOUNT).unwrap();
+        let pubkey = pubkey!("8CrWnjEJcXfz4BYaxoei9A6FHLWmwnr43SfV8juubnh1");
+
+        let account = receiver.get_account(&pubkey, None).await.unwrap();
+        let token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+
+        assert_eq!(account.owner, spl_token::id());
+        assert_eq!(token_account.mint, pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"));
+        assert_eq!(token_account.amount, 1_500_000);
+    }
+
+    #[tokio::test]
+    async fn fails_on_missing_accounts() {
+        let receiver = InMemoryAccountReceiver::default();
+        receiver.insert(Pubkey::new_unique(), Account::default());
+
+        assert!(receiver
+            .get_multiple_accounts(&[Pubkey::new_unique()], None)
+            .await
+            .is_err());
+    }
+}
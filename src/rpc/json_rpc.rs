@@ -2,27 +2,61 @@ use crate::rpc::JsonRpcAccountReceiver;
 use anyhow::Context;
 use async_trait::async_trait;
 use raydium_amm::solana_program::pubkey::Pubkey;
+use solana_account_decoder::UiAccountEncoding;
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
 use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
 use solana_sdk::commitment_config::CommitmentConfig;
 
+fn account_info_config(min_context_slot: Option<Slot>) -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64Zstd),
+        data_slice: None,
+        commitment: Some(CommitmentConfig::processed()),
+        min_context_slot,
+    }
+}
+
 #[async_trait]
 impl JsonRpcAccountReceiver for RpcClient {
-    async fn get_account(&self, pubkey: &Pubkey) -> anyhow::Result<Account> {
-        self.get_account_with_commitment(pubkey, CommitmentConfig::processed())
+    async fn get_account(&self, pubkey: &Pubkey, min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+        self.get_account_with_config(pubkey, account_info_config(min_context_slot))
             .await?
             .value
             .context("no account found")
     }
 
-    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> anyhow::Result<Vec<Account>> {
-        self.get_multiple_accounts(pubkeys)
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>> {
+        self.get_multiple_accounts_with_config(pubkeys, account_info_config(min_context_slot))
             .await?
+            .value
             .into_iter()
             .map(|account| account.context("no account found"))
             .collect::<Result<Vec<_>, _>>()
     }
 
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)> {
+        let response = self
+            .get_multiple_accounts_with_config(pubkeys, account_info_config(min_context_slot))
+            .await?;
+        let accounts = response
+            .value
+            .into_iter()
+            .map(|account| account.context("no account found"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((response.context.slot, accounts))
+    }
+
     async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
         Ok(self.get_account_data(pubkey).await?)
     }
@@ -0,0 +1,285 @@
+use crate::config::{GrpcEndpointMode, Rpc, StreamBackend};
+use crate::rpc::endpoint_race::{forward_first_arrivals, report_latencies, EndpointRace};
+use crate::rpc::subscription::{
+    AccountDataReceiverConf, ProgramFilter, StreamSource, SubscriptionCommand, TransactionFilter,
+};
+use crate::rpc::supervisor::{supervise_account_data_receiver, Backoff, ConnectionState, ConnectionStatuses};
+use crate::rpc::yellowstone_grpc::ConnectionConf;
+use crate::rpc::StreamEvent;
+use anyhow::{ensure, Context};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use tokio::spawn;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::RwLock;
+
+const COMMAND_CHANNEL_CAPACITY: usize = 64;
+const EVENT_CHANNEL_CAPACITY: usize = 32;
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Connections carrying the same filters, a single one unless endpoints race each other.
+struct Stream {
+    connection_names: Vec<String>,
+    commands: Vec<Sender<SubscriptionCommand>>,
+}
+
+struct Filter {
+    stream: usize,
+    accounts: Vec<Pubkey>,
+}
+
+/// Accounts carried by a stream and whether any of its connections is up.
+pub struct StreamHealth {
+    pub stream_name: String,
+    pub connected: bool,
+    pub accounts: BTreeSet<Pubkey>,
+}
+
+/// Spreads named account filters over a fixed number of supervised account streams.
+pub struct AccountStreamMultiplexer {
+    config: Rpc,
+    statuses: ConnectionStatuses,
+    streams: RwLock<Vec<Stream>>,
+    filters: RwLock<HashMap<String, Filter>>,
+}
+
+impl AccountStreamMultiplexer {
+    pub fn new(config: Rpc) -> Self {
+        Self {
+            config,
+            statuses: ConnectionStatuses::default(),
+            streams: RwLock::new(vec![]),
+            filters: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn streams_count(&self) -> usize {
+        self.config.yellowstone_grpc_streams.max(1)
+    }
+
+    /// Opens the streams, all of them feed `sender`.
+    pub async fn start(&self, sender: Sender<StreamEvent>) -> anyhow::Result<()> {
+        let mut streams = self.streams.write().await;
+        ensure!(streams.is_empty(), "account streams are already started");
+
+        let race = EndpointRace::default();
+        let racing = self.config.stream_backend == StreamBackend::YellowstoneGrpc
+            && self.config.yellowstone_grpc_mode == GrpcEndpointMode::Race;
+        let source = self.stream_source()?;
+        for index in 0..self.streams_count() {
+            let stream = if racing {
+                self.start_racing_stream(index, sender.clone(), &race)
+            } else {
+                self.start_stream(index, sender.clone(), source.clone())
+            };
+            streams.push(stream);
+        }
+
+        if racing {
+            spawn(report_latencies(race, LATENCY_REPORT_INTERVAL));
+        }
+
+        Ok(())
+    }
+
+    fn stream_source(&self) -> anyhow::Result<StreamSource> {
+        match self.config.stream_backend {
+            StreamBackend::YellowstoneGrpc => {
+                let endpoints = self.config.yellowstone_grpc_endpoints.clone();
+                ensure!(!endpoints.is_empty(), "no Yellowstone gRPC endpoints configured");
+                Ok(StreamSource::YellowstoneGrpc { endpoints, connection: ConnectionConf::from(&self.config) })
+            }
+            StreamBackend::WebSocket => {
+                let url = self
+                    .config
+                    .websocket_endpoint
+                    .clone()
+                    .context("no WebSocket endpoint configured")?;
+                Ok(StreamSource::WebSocket { url })
+            }
+        }
+    }
+
+    fn start_stream(&self, index: usize, sender: Sender<StreamEvent>, source: StreamSource) -> Stream {
+        let (commands_tx, commands_rx) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+        let conf = AccountDataReceiverConf { stream_name: stream_name(index), sender, source };
+
+        spawn(supervise_account_data_receiver(conf, commands_rx, self.statuses.clone(), Backoff::default()));
+        Stream { connection_names: vec![stream_name(index)], commands: vec![commands_tx] }
+    }
+
+    /// One connection per endpoint, only the first arrival of every update reaches `sender`.
+    fn start_racing_stream(&self, index: usize, sender: Sender<StreamEvent>, race: &EndpointRace) -> Stream {
+        let endpoints = &self.config.yellowstone_grpc_endpoints;
+        let connection_names = endpoints
+            .iter()
+            .map(|endpoint| format!("{stream_name}@{url}", stream_name = stream_name(index), url = endpoint.url))
+            .collect::<Vec<_>>();
+
+        let mut commands = Vec::with_capacity(endpoints.len());
+        for (endpoint, connection_name) in endpoints.iter().zip(&connection_names) {
+            let (commands_tx, commands_rx) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+            let (events_tx, events_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+            let conf = AccountDataReceiverConf {
+                stream_name: connection_name.clone(),
+                sender: events_tx,
+                source: StreamSource::YellowstoneGrpc {
+                    endpoints: vec![endpoint.clone()],
+                    connection: ConnectionConf::from(&self.config),
+                },
+            };
+            let sibling_connections = connection_names
+                .iter()
+                .filter(|name| *name != connection_name)
+                .cloned()
+                .collect();
+
+            spawn(supervise_account_data_receiver(conf, commands_rx, self.statuses.clone(), Backoff::default()));
+            spawn(forward_first_arrivals(
+                race.clone(),
+                endpoint.url.clone(),
+                events_rx,
+                sender.clone(),
+                sibling_connections,
+                self.statuses.clone(),
+            ));
+            commands.push(commands_tx);
+        }
+
+        Stream { connection_names, commands }
+    }
+
+    /// Subscribes the accounts under `filter_name`, replacing the accounts it had before.
+    ///
+    /// A new filter goes to the stream carrying the fewest filters.
+    pub async fn set(&self, filter_name: &str, account_addresses: Vec<Pubkey>) -> anyhow::Result<()> {
+        let index = self.assign_stream(filter_name, account_addresses.clone()).await;
+
+        let command = SubscriptionCommand::Set { filter_name: filter_name.to_string(), account_addresses };
+        self.send(index, command).await
+    }
+
+    /// Subscribes every account matching the program filter under `filter_name`.
+    ///
+    /// The matching accounts are unknown upfront, so they are not polled while the stream is down.
+    pub async fn set_program(&self, filter_name: &str, filter: ProgramFilter) -> anyhow::Result<()> {
+        let index = self.assign_stream(filter_name, vec![]).await;
+
+        self.send(index, SubscriptionCommand::SetProgram { filter_name: filter_name.to_string(), filter })
+            .await
+    }
+
+    /// Streams the transactions matching the filter under `filter_name`.
+    pub async fn set_transactions(&self, filter_name: &str, filter: TransactionFilter) -> anyhow::Result<()> {
+        let index = self.assign_stream(filter_name, vec![]).await;
+
+        self.send(index, SubscriptionCommand::SetTransactions { filter_name: filter_name.to_string(), filter })
+            .await
+    }
+
+    async fn assign_stream(&self, filter_name: &str, accounts: Vec<Pubkey>) -> usize {
+        let mut filters = self.filters.write().await;
+        let index = match filters.get(filter_name) {
+            Some(filter) => filter.stream,
+            None => (0..self.streams_count())
+                .min_by_key(|index| filters.values().filter(|filter| filter.stream == *index).count())
+                .unwrap_or_default(),
+        };
+        filters.insert(filter_name.to_string(), Filter { stream: index, accounts });
+        index
+    }
+
+    pub async fn remove(&self, filter_name: &str) -> anyhow::Result<()> {
+        let Some(filter) = self.filters.write().await.remove(filter_name) else {
+            return Ok(());
+        };
+
+        self.send(filter.stream, SubscriptionCommand::Remove { filter_name: filter_name.to_string() })
+            .await
+    }
+
+    /// State of the stream carrying `filter_name`, a racing stream is as good as its best connection.
+    pub async fn connection_state(&self, filter_name: &str) -> Option<ConnectionState> {
+        let index = self.filters.read().await.get(filter_name)?.stream;
+        self.stream_state(index).await
+    }
+
+    async fn stream_state(&self, index: usize) -> Option<ConnectionState> {
+        let connection_names = self.streams.read().await.get(index)?.connection_names.clone();
+
+        let mut states = vec![];
+        for connection_name in &connection_names {
+            states.extend(self.statuses.get(connection_name).await);
+        }
+
+        let connected = states
+            .iter()
+            .position(|state| matches!(state, ConnectionState::Connected { .. }));
+        connected
+            .map(|position| states.swap_remove(position))
+            .or_else(|| states.into_iter().next())
+    }
+
+    pub async fn health(&self) -> Vec<StreamHealth> {
+        let streams_count = self.streams.read().await.len();
+        let mut health = Vec::with_capacity(streams_count);
+
+        for index in 0..streams_count {
+            let connected = matches!(self.stream_state(index).await, Some(ConnectionState::Connected { .. }));
+            let accounts = self
+                .filters
+                .read()
+                .await
+                .values()
+                .filter(|filter| filter.stream == index)
+                .flat_map(|filter| filter.accounts.iter().copied())
+                .collect();
+            health.push(StreamHealth { stream_name: stream_name(index), connected, accounts });
+        }
+
+        health
+    }
+
+    /// Filters carried by the stream the connection named `connection_name` belongs to.
+    pub async fn filters_of(&self, connection_name: &str) -> Vec<String> {
+        let streams = self.streams.read().await;
+        let Some(index) = streams
+            .iter()
+            .position(|stream| stream.connection_names.iter().any(|name| name == connection_name))
+        else {
+            return vec![];
+        };
+
+        self.filters
+            .read()
+            .await
+            .iter()
+            .filter(|(_, filter)| filter.stream == index)
+            .map(|(filter_name, _)| filter_name.clone())
+            .collect()
+    }
+
+    async fn send(&self, index: usize, command: SubscriptionCommand) -> anyhow::Result<()> {
+        let commands = self
+            .streams
+            .read()
+            .await
+            .get(index)
+            .map(|stream| stream.commands.clone());
+
+        for connection in commands.context("account streams are not started")? {
+            connection
+                .send(command.clone())
+                .await
+                .ok()
+                .context("account stream is stopped")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn stream_name(index: usize) -> String {
+    format!("accounts-{index}")
+}
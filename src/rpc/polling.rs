@@ -0,0 +1,138 @@
+use crate::config::Rpc;
+use crate::rpc::multiplexer::AccountStreamMultiplexer;
+use crate::rpc::{AccountUpdate, JsonRpcAccountReceiver, StreamEvent};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use tracing::{info, warn};
+
+#[derive(Clone, Debug)]
+pub struct PollingConf {
+    pub interval: Duration,
+    pub fallback_after: Duration,
+}
+
+impl From<&Rpc> for PollingConf {
+    fn from(config: &Rpc) -> Self {
+        Self {
+            interval: Duration::from_millis(config.json_rpc_polling_interval_ms.max(1)),
+            fallback_after: Duration::from_secs(config.json_rpc_polling_fallback_after_secs),
+        }
+    }
+}
+
+/// Decides which streams are down for long enough to have their accounts polled.
+struct FallbackSwitch {
+    fallback_after: Duration,
+    disconnected_since: HashMap<String, Instant>,
+    polled_streams: HashSet<String>,
+}
+
+impl FallbackSwitch {
+    fn new(fallback_after: Duration) -> Self {
+        Self {
+            fallback_after,
+            disconnected_since: HashMap::new(),
+            polled_streams: HashSet::new(),
+        }
+    }
+
+    fn should_poll(&mut self, stream_name: &str, connected: bool, now: Instant) -> bool {
+        if connected {
+            self.disconnected_since.remove(stream_name);
+            if self.polled_streams.remove(stream_name) {
+                info!("Stream '{stream_name}' is connected again, stopped polling its accounts");
+            }
+            return false;
+        }
+
+        let disconnected_since = *self.disconnected_since.entry(stream_name.to_string()).or_insert(now);
+        if now.duration_since(disconnected_since) < self.fallback_after {
+            return false;
+        }
+
+        if self.polled_streams.insert(stream_name.to_string()) {
+            warn!("Stream '{stream_name}' is down for {:?}, polling its accounts instead", self.fallback_after);
+        }
+        true
+    }
+}
+
+/// Feeds `sender` with accounts of the streams that are down, read through `get_multiple_accounts` every interval.
+///
+/// Only accounts whose data changed since the previous poll are passed on.
+pub async fn poll_disconnected_streams(
+    conf: PollingConf,
+    json_rpc_account_receiver: Arc<dyn JsonRpcAccountReceiver + Send + Sync>,
+    account_streams: Arc<AccountStreamMultiplexer>,
+    sender: Sender<StreamEvent>,
+) {
+    let mut switch = FallbackSwitch::new(conf.fallback_after);
+    let mut last_polled = HashMap::<Pubkey, Vec<u8>>::new();
+    let mut poll_interval = interval(conf.interval);
+    poll_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        poll_interval.tick().await;
+
+        let now = Instant::now();
+        let mut accounts = BTreeSet::new();
+        for stream in account_streams.health().await {
+            if switch.should_poll(&stream.stream_name, stream.connected, now) {
+                accounts.extend(stream.accounts);
+            }
+        }
+
+        // Accounts that are streamed again are forgotten, the next fallback starts with all of them.
+        last_polled.retain(|pubkey, _| accounts.contains(pubkey));
+        if accounts.is_empty() {
+            continue;
+        }
+
+        let accounts = accounts.into_iter().collect::<Vec<_>>();
+        let (slot, polled_accounts) = match json_rpc_account_receiver
+            .get_multiple_accounts_with_slot(&accounts, None)
+            .await
+        {
+            Ok(polled) => polled,
+            Err(e) => {
+                warn!("Failed to poll {count} accounts: {e}", count = accounts.len());
+                continue;
+            }
+        };
+
+        for (pubkey, account) in accounts.into_iter().zip(polled_accounts) {
+            if last_polled.get(&pubkey) == Some(&account.data) {
+                continue;
+            }
+            last_polled.insert(pubkey, account.data.clone());
+
+            let update = AccountUpdate { pubkey, owner: account.owner, data: account.data, slot, write_version: 0 };
+            if sender.send(StreamEvent::Account(update)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polls_streams_down_for_too_long_until_they_reconnect() {
+        let mut switch = FallbackSwitch::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(!switch.should_poll("accounts-0", false, start));
+        assert!(!switch.should_poll("accounts-0", false, start + Duration::from_secs(9)));
+        assert!(switch.should_poll("accounts-0", false, start + Duration::from_secs(10)));
+        assert!(!switch.should_poll("accounts-1", false, start + Duration::from_secs(10)));
+
+        assert!(!switch.should_poll("accounts-0", true, start + Duration::from_secs(11)));
+        assert!(!switch.should_poll("accounts-0", false, start + Duration::from_secs(12)));
+    }
+}
@@ -0,0 +1,308 @@
+use crate::config::Rpc;
+use crate::rpc::supervisor::Backoff;
+use crate::rpc::JsonRpcAccountReceiver;
+use anyhow::ensure;
+use async_trait::async_trait;
+use solana_client::client_error::reqwest::StatusCode;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_custom_error::{
+    JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+};
+use solana_client::rpc_request::RpcError;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{sleep, sleep_until, Instant};
+use tracing::warn;
+
+/// Most accounts a node returns from a single `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+type Endpoint = Arc<dyn JsonRpcAccountReceiver + Send + Sync>;
+
+/// Spaces requests evenly to stay within a requests-per-second budget shared by all endpoints.
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    next_request_at: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / requests_per_second.max(1),
+            next_request_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub(crate) async fn acquire(&self) {
+        let request_at = {
+            let mut next_request_at = self.next_request_at.lock().expect("rate limiter state is poisoned");
+            let request_at = (*next_request_at).max(Instant::now());
+            *next_request_at = request_at + self.interval;
+            request_at
+        };
+
+        sleep_until(request_at).await;
+    }
+}
+
+/// Wraps several JSON-RPC endpoints, every request goes to the next one in turn.
+///
+/// Transient errors are retried with backoff on the following endpoints and `get_multiple_accounts` requests are
+/// split into chunks the nodes accept.
+pub struct ResilientJsonRpcAccountReceiver {
+    endpoints: Vec<Endpoint>,
+    next_endpoint: AtomicUsize,
+    rate_limiter: Option<RateLimiter>,
+    max_retries: u32,
+    backoff: Backoff,
+}
+
+impl ResilientJsonRpcAccountReceiver {
+    pub fn new(endpoints: Vec<Endpoint>, requests_per_second: Option<u32>, max_retries: u32) -> anyhow::Result<Self> {
+        ensure!(!endpoints.is_empty(), "at least one JSON-RPC endpoint is required");
+
+        Ok(Self {
+            endpoints,
+            next_endpoint: AtomicUsize::new(0),
+            rate_limiter: requests_per_second.map(RateLimiter::new),
+            max_retries,
+            backoff: Backoff { initial: Duration::from_millis(200), max: Duration::from_secs(5) },
+        })
+    }
+
+    pub fn from_config(config: &Rpc) -> anyhow::Result<Self> {
+        let endpoints = [&config.json_rpc_endpoint]
+            .into_iter()
+            .chain(&config.json_rpc_additional_endpoints)
+            .map(|url| Arc::new(RpcClient::new(url.clone())) as Endpoint)
+            .collect();
+
+        Self::new(endpoints, config.json_rpc_requests_per_second, config.json_rpc_max_retries)
+    }
+
+    async fn request<T, F, Fut>(&self, request: F) -> anyhow::Result<T>
+    where
+        F: Fn(Endpoint) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let endpoint = self.next_endpoint.fetch_add(1, Ordering::Relaxed) % self.endpoints.len();
+            match request(self.endpoints[endpoint].clone()).await {
+                Err(e) if attempt < self.max_retries && is_transient(&e) => {
+                    let retry_in = self.backoff.delay(attempt);
+                    warn!("JSON-RPC request failed, retrying in {retry_in:?}: {e}");
+                    attempt += 1;
+                    sleep(retry_in).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Errors a later request, possibly to another endpoint, may not run into.
+fn is_transient(error: &anyhow::Error) -> bool {
+    let Some(error) = error.downcast_ref::<ClientError>() else {
+        return false;
+    };
+
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(error) => error
+            .status()
+            .map_or(true, |status| status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => [
+            JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+            JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+        ]
+        .contains(code),
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl JsonRpcAccountReceiver for ResilientJsonRpcAccountReceiver {
+    async fn get_account(&self, pubkey: &Pubkey, min_context_slot: Option<Slot>) -> anyhow::Result<Account> {
+        self.request(|endpoint| async move { endpoint.get_account(pubkey, min_context_slot).await })
+            .await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<Vec<Account>> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let chunk_accounts = self
+                .request(|endpoint| async move { endpoint.get_multiple_accounts(chunk, min_context_slot).await })
+                .await?;
+            accounts.extend(chunk_accounts);
+        }
+
+        Ok(accounts)
+    }
+
+    /// Chunks after the first one are read at its slot or later, which is the slot returned.
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+    ) -> anyhow::Result<(Slot, Vec<Account>)> {
+        let (mut slot, mut accounts) = (None, Vec::with_capacity(pubkeys.len()));
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let chunk_min_context_slot = min_context_slot.max(slot);
+            let (chunk_slot, chunk_accounts) = self
+                .request(|endpoint| async move {
+                    endpoint
+                        .get_multiple_accounts_with_slot(chunk, chunk_min_context_slot)
+                        .await
+                })
+                .await?;
+            slot = slot.or(Some(chunk_slot));
+            accounts.extend(chunk_accounts);
+        }
+
+        Ok((slot.or(min_context_slot).unwrap_or_default(), accounts))
+    }
+
+    async fn get_account_data(&self, pubkey: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        self.request(|endpoint| async move { endpoint.get_account_data(pubkey).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::io;
+    use std::sync::atomic::AtomicU32;
+
+    /// Fails the first `failures` requests, either with a transient or a permanent error.
+    struct FlakyReceiver {
+        failures: AtomicU32,
+        transient: bool,
+        requests: Mutex<Vec<usize>>,
+    }
+
+    impl FlakyReceiver {
+        fn failing(failures: u32, transient: bool) -> Arc<Self> {
+            Arc::new(Self { failures: AtomicU32::new(failures), transient, requests: Mutex::default() })
+        }
+
+        fn request(&self, pubkeys_count: usize) -> anyhow::Result<()> {
+            self.requests.lock().unwrap().push(pubkeys_count);
+            if self.failures.load(Ordering::Relaxed) == 0 {
+                return Ok(());
+            }
+
+            self.failures.fetch_sub(1, Ordering::Relaxed);
+
+            if self.transient {
+                Err(ClientError::from(ClientErrorKind::Io(io::ErrorKind::ConnectionReset.into())).into())
+            } else {
+                Err(anyhow!("no account found"))
+            }
+        }
+
+        fn requests(&self) -> Vec<usize> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl JsonRpcAccountReceiver for FlakyReceiver {
+        async fn get_account(&self, _: &Pubkey, _: Option<Slot>) -> anyhow::Result<Account> {
+            self.request(1)?;
+            Ok(Account::default())
+        }
+
+        async fn get_multiple_accounts(&self, pubkeys: &[Pubkey], _: Option<Slot>) -> anyhow::Result<Vec<Account>> {
+            self.request(pubkeys.len())?;
+            Ok(vec![Account::default(); pubkeys.len()])
+        }
+
+        async fn get_multiple_accounts_with_slot(
+            &self,
+            pubkeys: &[Pubkey],
+            min_context_slot: Option<Slot>,
+        ) -> anyhow::Result<(Slot, Vec<Account>)> {
+            self.request(pubkeys.len())?;
+            Ok((min_context_slot.unwrap_or(10) + 1, vec![Account::default(); pubkeys.len()]))
+        }
+
+        async fn get_account_data(&self, _: &Pubkey) -> anyhow::Result<Vec<u8>> {
+            self.request(1)?;
+            Ok(vec![])
+        }
+    }
+
+    fn pubkeys(count: usize) -> Vec<Pubkey> {
+        (0..count).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[tokio::test]
+    async fn splits_multiple_accounts_into_chunks() {
+        let endpoint = FlakyReceiver::failing(0, false);
+        let receiver = ResilientJsonRpcAccountReceiver::new(vec![endpoint.clone() as Endpoint], None, 0).unwrap();
+
+        let (slot, accounts) = receiver
+            .get_multiple_accounts_with_slot(&pubkeys(250), None)
+            .await
+            .unwrap();
+
+        assert_eq!(accounts.len(), 250);
+        assert_eq!(endpoint.requests(), vec![100, 100, 50]);
+        assert_eq!(slot, 11);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_on_the_next_endpoint() {
+        let (failing, healthy) = (FlakyReceiver::failing(1, true), FlakyReceiver::failing(0, true));
+        let endpoints = vec![failing.clone() as Endpoint, healthy.clone()];
+        let receiver = ResilientJsonRpcAccountReceiver::new(endpoints, None, 2).unwrap();
+
+        receiver.get_account(&Pubkey::new_unique(), None).await.unwrap();
+
+        assert_eq!((failing.requests().len(), healthy.requests().len()), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_permanent_errors() {
+        let endpoint = FlakyReceiver::failing(1, false);
+        let receiver = ResilientJsonRpcAccountReceiver::new(vec![endpoint.clone() as Endpoint], None, 3).unwrap();
+
+        assert!(receiver.get_account(&Pubkey::new_unique(), None).await.is_err());
+        assert_eq!(endpoint.requests().len(), 1);
+    }
+
+    #[test]
+    fn requires_an_endpoint() {
+        assert!(ResilientJsonRpcAccountReceiver::new(vec![], None, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn spaces_requests_to_the_budget() {
+        let rate_limiter = RateLimiter::new(50);
+        let started_at = Instant::now();
+
+        for _ in 0..3 {
+            rate_limiter.acquire().await;
+        }
+
+        assert!(started_at.elapsed() >= Duration::from_millis(40));
+    }
+}
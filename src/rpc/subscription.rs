@@ -0,0 +1,123 @@
+use crate::config::GrpcEndpoint;
+use crate::rpc::yellowstone_grpc::ConnectionConf;
+use crate::rpc::StreamEvent;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::mpsc::Sender;
+
+pub struct AccountDataReceiverConf {
+    pub stream_name: String,
+    pub sender: Sender<StreamEvent>,
+    pub source: StreamSource,
+}
+
+/// Where the account updates of a stream come from.
+#[derive(Clone)]
+pub enum StreamSource {
+    /// Endpoints in the order they are tried.
+    YellowstoneGrpc { endpoints: Vec<GrpcEndpoint>, connection: ConnectionConf },
+    /// Solana PubSub `accountSubscribe`, one subscription per account.
+    WebSocket { url: String },
+}
+
+impl StreamSource {
+    pub fn endpoints_count(&self) -> usize {
+        match self {
+            StreamSource::YellowstoneGrpc { endpoints, .. } => endpoints.len(),
+            StreamSource::WebSocket { .. } => 1,
+        }
+    }
+}
+
+/// Every account owned by `owner` whose data is `data_size` bytes long.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramFilter {
+    pub owner: Pubkey,
+    pub data_size: u64,
+}
+
+/// Successful non-vote transactions referencing any of `accounts`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionFilter {
+    pub accounts: Vec<Pubkey>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubscriptionCommand {
+    /// Adds the named filter or replaces its accounts.
+    Set {
+        filter_name: String,
+        account_addresses: Vec<Pubkey>,
+    },
+    /// Adds the named program filter or replaces it, only Yellowstone streams support them.
+    SetProgram {
+        filter_name: String,
+        filter: ProgramFilter,
+    },
+    /// Adds the named transaction filter or replaces it, only Yellowstone streams support them.
+    SetTransactions {
+        filter_name: String,
+        filter: TransactionFilter,
+    },
+    Remove {
+        filter_name: String,
+    },
+}
+
+/// Named account and transaction filters carried by a single stream.
+#[derive(Clone, Debug, Default)]
+pub struct AccountFilters {
+    filters: HashMap<String, Vec<Pubkey>>,
+    programs: HashMap<String, ProgramFilter>,
+    transactions: HashMap<String, TransactionFilter>,
+}
+
+impl AccountFilters {
+    pub fn apply(&mut self, command: SubscriptionCommand) {
+        match command {
+            SubscriptionCommand::Set { filter_name, account_addresses } => {
+                self.filters.insert(filter_name, account_addresses);
+            }
+            SubscriptionCommand::SetProgram { filter_name, filter } => {
+                self.programs.insert(filter_name, filter);
+            }
+            SubscriptionCommand::SetTransactions { filter_name, filter } => {
+                self.transactions.insert(filter_name, filter);
+            }
+            SubscriptionCommand::Remove { filter_name } => {
+                self.filters.remove(&filter_name);
+                self.programs.remove(&filter_name);
+                self.transactions.remove(&filter_name);
+            }
+        }
+    }
+
+    pub fn filters(&self) -> impl Iterator<Item = (&String, &Vec<Pubkey>)> {
+        self.filters.iter()
+    }
+
+    pub fn programs(&self) -> impl Iterator<Item = (&String, &ProgramFilter)> {
+        self.programs.iter()
+    }
+
+    pub fn transactions(&self) -> impl Iterator<Item = (&String, &TransactionFilter)> {
+        self.transactions.iter()
+    }
+
+    /// Accounts of all filters, each one once.
+    pub fn accounts(&self) -> BTreeSet<Pubkey> {
+        self.filters.values().flatten().copied().collect()
+    }
+}
+
+/// What a stream has to remember between connections.
+#[derive(Clone, Debug, Default)]
+pub struct StreamState {
+    pub filters: AccountFilters,
+    /// Slot of the last account update received, missed updates are those after it.
+    pub last_slot: Option<Slot>,
+    pub subscribed_before: bool,
+    /// Number of updates received from sources without write versions, it orders their updates within a slot.
+    pub received_updates: u64,
+}
@@ -0,0 +1,197 @@
+use crate::rpc::subscription::{AccountDataReceiverConf, StreamSource, StreamState, SubscriptionCommand};
+use crate::rpc::{websocket, yellowstone_grpc};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected { endpoint: String, since: DateTime<Utc> },
+    Reconnecting { attempt: u32, retry_in: Duration, last_error: String },
+    Stopped,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected { endpoint, since } => write!(f, "connected to {endpoint} since {since}"),
+            ConnectionState::Reconnecting { attempt, retry_in, last_error } => write!(
+                f,
+                "reconnecting (attempt {attempt}, retry in {retry_in_ms} ms) after: {last_error}",
+                retry_in_ms = retry_in.as_millis()
+            ),
+            ConnectionState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// Connection state of every supervised stream, keyed by the stream name.
+#[derive(Clone, Default)]
+pub struct ConnectionStatuses {
+    states: Arc<RwLock<HashMap<String, ConnectionState>>>,
+}
+
+impl ConnectionStatuses {
+    pub async fn set(&self, stream_name: &str, state: ConnectionState) {
+        info!("Stream '{stream_name}' is {state}");
+        self.states.write().await.insert(stream_name.to_string(), state);
+    }
+
+    pub async fn get(&self, stream_name: &str) -> Option<ConnectionState> {
+        self.states.read().await.get(stream_name).cloned()
+    }
+}
+
+/// Exponential backoff with equal jitter: half of the delay is fixed, the other half is random.
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self { initial: Duration::from_millis(500), max: Duration::from_secs(30) }
+    }
+}
+
+impl Backoff {
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self.initial.saturating_mul(2u32.saturating_pow(attempt)).min(self.max);
+        let half = ceiling / 2;
+
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// Picks the endpoint of the next connection and the backoff before it.
+#[derive(Debug, Default)]
+struct Reconnects {
+    /// Connections that failed since the last working session.
+    failures: u32,
+}
+
+impl Reconnects {
+    /// Endpoints are dialed in priority order.
+    fn endpoint(&self, endpoints_count: usize) -> usize {
+        self.failures as usize % endpoints_count
+    }
+
+    /// Records the end of a connection, returns the backoff attempt to wait for.
+    fn session_ended(&mut self, connected: bool, endpoints_count: usize) -> u32 {
+        // A stream that got as far as subscribing starts over from the first endpoint with a fresh backoff sequence.
+        if connected {
+            self.failures = 0;
+            return 0;
+        }
+
+        // Every endpoint of a round waits the same, the delay grows once all of them failed.
+        let round = self.failures / endpoints_count as u32;
+        self.failures = self.failures.saturating_add(1);
+        round
+    }
+}
+
+/// Keeps the account stream of `conf` alive until the receiving side of its channel is dropped.
+///
+/// Filters survive reconnects, so every new connection resubscribes to everything added so far.
+/// Endpoints are tried in order, each reconnect after a working session starts over from the first one.
+pub async fn supervise_account_data_receiver(
+    conf: AccountDataReceiverConf,
+    mut commands: Receiver<SubscriptionCommand>,
+    statuses: ConnectionStatuses,
+    backoff: Backoff,
+) {
+    let (mut state, mut reconnects) = (StreamState::default(), Reconnects::default());
+
+    loop {
+        while let Ok(command) = commands.try_recv() {
+            state.filters.apply(command);
+        }
+
+        let endpoints_count = conf.source.endpoints_count();
+        if endpoints_count == 0 {
+            warn!("Stream '{stream_name}' has no endpoints", stream_name = conf.stream_name);
+            statuses.set(&conf.stream_name, ConnectionState::Stopped).await;
+            return;
+        }
+
+        statuses.set(&conf.stream_name, ConnectionState::Connecting).await;
+        let result = match &conf.source {
+            StreamSource::YellowstoneGrpc { endpoints, connection } => {
+                let endpoint = &endpoints[reconnects.endpoint(endpoints_count)];
+                yellowstone_grpc::get_account_data(&conf, endpoint, connection, &mut state, &mut commands, &statuses)
+                    .await
+            }
+            StreamSource::WebSocket { url } => {
+                websocket::get_account_data(&conf, url, &mut state, &mut commands, &statuses).await
+            }
+        };
+
+        if conf.sender.is_closed() {
+            statuses.set(&conf.stream_name, ConnectionState::Stopped).await;
+            return;
+        }
+
+        let connected = matches!(statuses.get(&conf.stream_name).await, Some(ConnectionState::Connected { .. }));
+        let retry_in = backoff.delay(reconnects.session_ended(connected, endpoints_count));
+        let attempt = reconnects.failures;
+        let last_error = result
+            .err()
+            .map_or_else(|| "stream ended".to_string(), |e| e.to_string());
+        warn!("Stream '{stream_name}' disconnected: {last_error}", stream_name = conf.stream_name);
+
+        statuses
+            .set(&conf.stream_name, ConnectionState::Reconnecting { attempt, retry_in, last_error })
+            .await;
+        sleep(retry_in).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redials_the_primary_after_a_working_session() {
+        let mut reconnects = Reconnects::default();
+        assert_eq!(reconnects.endpoint(2), 0);
+
+        // The primary drops a working session.
+        assert_eq!(reconnects.session_ended(true, 2), 0);
+        assert_eq!(reconnects.endpoint(2), 0);
+
+        // Then fails to connect, the backup follows and the delay grows once both failed.
+        assert_eq!(reconnects.session_ended(false, 2), 0);
+        assert_eq!(reconnects.endpoint(2), 1);
+        assert_eq!(reconnects.session_ended(false, 2), 0);
+        assert_eq!(reconnects.endpoint(2), 0);
+        assert_eq!(reconnects.session_ended(false, 2), 1);
+
+        // A working session on the backup starts over from the primary.
+        assert_eq!(reconnects.endpoint(2), 1);
+        reconnects.session_ended(true, 2);
+        assert_eq!(reconnects.endpoint(2), 0);
+    }
+
+    #[test]
+    fn backoff_grows_up_to_max() {
+        let backoff = Backoff { initial: Duration::from_millis(100), max: Duration::from_secs(1) };
+
+        for (attempt, ceiling) in [(0, 100), (1, 200), (3, 800), (4, 1_000), (40, 1_000)] {
+            let delay = backoff.delay(attempt);
+            assert!(delay >= Duration::from_millis(ceiling / 2), "attempt {attempt}: {delay:?}");
+            assert!(delay <= Duration::from_millis(ceiling), "attempt {attempt}: {delay:?}");
+        }
+    }
+}
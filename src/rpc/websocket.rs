@@ -0,0 +1,272 @@
+use crate::rpc::subscription::{AccountDataReceiverConf, StreamState, SubscriptionCommand};
+use crate::rpc::supervisor::{ConnectionState, ConnectionStatuses};
+use crate::rpc::{AccountUpdate, StreamEvent};
+use anyhow::{bail, Context};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use futures::stream::{iter, BoxStream, SelectAll};
+use futures::StreamExt;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_client::rpc_response::Response;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use tokio::select;
+use tokio::sync::mpsc::Receiver;
+use tracing::{debug, info, warn};
+
+/// A notification tagged with its account and subscription generation, `None` marks the end of the subscription.
+type Notification = (Pubkey, u64, Option<Response<UiAccount>>);
+type Unsubscribe = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// `accountSubscribe` subscriptions of a single PubSub connection, one per account.
+struct Subscriptions<'a> {
+    client: &'a PubsubClient,
+    notifications: SelectAll<BoxStream<'a, Notification>>,
+    /// The generation tells the end of a replaced subscription apart from the end of a live one.
+    live: HashMap<Pubkey, (u64, Unsubscribe)>,
+    generation: u64,
+}
+
+impl<'a> Subscriptions<'a> {
+    fn new(client: &'a PubsubClient) -> Self {
+        Self { client, notifications: SelectAll::new(), live: HashMap::new(), generation: 0 }
+    }
+
+    /// Subscribes the accounts not subscribed yet and unsubscribes those missing from `accounts`.
+    async fn sync(&mut self, accounts: &BTreeSet<Pubkey>) -> anyhow::Result<()> {
+        let removed = self
+            .live
+            .keys()
+            .filter(|pubkey| !accounts.contains(pubkey))
+            .copied()
+            .collect::<Vec<_>>();
+        for pubkey in removed {
+            if let Some((_, unsubscribe)) = self.live.remove(&pubkey) {
+                unsubscribe().await;
+            }
+        }
+
+        for pubkey in accounts {
+            if !self.live.contains_key(pubkey) {
+                self.subscribe(*pubkey).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe(&mut self, pubkey: Pubkey) -> anyhow::Result<()> {
+        let client = self.client;
+        let (notifications, unsubscribe) = client
+            .account_subscribe(&pubkey, Some(account_subscribe_config()))
+            .await
+            .with_context(|| format!("Failed to subscribe to account {pubkey}"))?;
+
+        self.generation += 1;
+        let generation = self.generation;
+        self.notifications.push(
+            notifications
+                .map(move |notification| (pubkey, generation, Some(notification)))
+                .chain(iter([(pubkey, generation, None)]))
+                .boxed(),
+        );
+        self.live.insert(pubkey, (generation, unsubscribe));
+
+        Ok(())
+    }
+
+    fn is_live(&self, pubkey: &Pubkey, generation: u64) -> bool {
+        self.live
+            .get(pubkey)
+            .is_some_and(|(live_generation, _)| *live_generation == generation)
+    }
+}
+
+fn account_subscribe_config() -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: None,
+        commitment: Some(CommitmentConfig::processed()),
+        min_context_slot: None,
+    }
+}
+
+/// Streams account updates of all filters over Solana PubSub until the connection fails or the receiver is dropped.
+///
+/// Behaves like the Yellowstone stream towards the supervisor: commands update the subscriptions of the live
+/// connection and a resubscription is announced with `StreamEvent::Resubscribed`.
+pub async fn get_account_data(
+    conf: &AccountDataReceiverConf,
+    url: &str,
+    state: &mut StreamState,
+    commands: &mut Receiver<SubscriptionCommand>,
+    statuses: &ConnectionStatuses,
+) -> anyhow::Result<()> {
+    let client = PubsubClient::new(url)
+        .await
+        .with_context(|| format!("Failed to connect to {url}"))?;
+    let result = stream_accounts(&client, conf, url, state, commands, statuses).await;
+
+    // The client keeps its connection open after being dropped, it has to be closed explicitly.
+    let _ = client.shutdown().await;
+    result
+}
+
+async fn stream_accounts(
+    client: &PubsubClient,
+    conf: &AccountDataReceiverConf,
+    url: &str,
+    state: &mut StreamState,
+    commands: &mut Receiver<SubscriptionCommand>,
+    statuses: &ConnectionStatuses,
+) -> anyhow::Result<()> {
+    let mut subscriptions = Subscriptions::new(client);
+    subscriptions.sync(&state.filters.accounts()).await?;
+
+    info!("Subscribed to accounts of '{stream_name}' at {url}", stream_name = conf.stream_name);
+    statuses
+        .set(&conf.stream_name, ConnectionState::Connected { endpoint: url.to_string(), since: Utc::now() })
+        .await;
+
+    if state.subscribed_before {
+        let event = StreamEvent::Resubscribed { stream_name: conf.stream_name.clone(), last_slot: state.last_slot };
+        if conf.sender.send(event).await.is_err() {
+            return Ok(());
+        }
+    }
+    state.subscribed_before = true;
+
+    loop {
+        let (pubkey, generation, notification) = select! {
+            Some(command) = commands.recv() => {
+                debug!("Updating subscription of '{stream_name}': {command:?}", stream_name = conf.stream_name);
+                if let SubscriptionCommand::SetProgram { filter_name, .. }
+                | SubscriptionCommand::SetTransactions { filter_name, .. } = &command
+                {
+                    warn!("Ignoring filter '{filter_name}', PubSub streams only subscribe to single accounts");
+                }
+                state.filters.apply(command);
+                subscriptions.sync(&state.filters.accounts()).await?;
+                continue;
+            }
+            Some(notification) = subscriptions.notifications.next() => notification,
+            else => return Ok(()),
+        };
+
+        let Some(notification) = notification else {
+            if subscriptions.is_live(&pubkey, generation) {
+                bail!("subscription to {pubkey} closed by the server");
+            }
+            continue;
+        };
+        let Some(account) = notification.value.decode::<Account>() else {
+            warn!("Received malformed account update for {}", conf.stream_name);
+            continue;
+        };
+        debug!("Received update {pubkey}");
+
+        // PubSub has no write versions, arrival order stands in for them.
+        state.received_updates += 1;
+        let update = AccountUpdate {
+            pubkey,
+            owner: account.owner,
+            data: account.data,
+            slot: notification.context.slot,
+            write_version: state.received_updates,
+        };
+        state.last_slot = state.last_slot.max(Some(update.slot));
+        if conf.sender.send(StreamEvent::Account(update)).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::subscription::StreamSource;
+    use futures::SinkExt;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio::sync::{mpsc, oneshot};
+    use tokio_tungstenite::accept_async;
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// Answers the first `accountSubscribe` and pushes a single notification, the connection drops on `disconnect`.
+    async fn mock_pubsub_server(owner: Pubkey, disconnect: oneshot::Receiver<()>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(socket).await.unwrap();
+
+            let Some(Ok(Message::Text(request))) = ws.next().await else {
+                panic!("expected an accountSubscribe request");
+            };
+            let request: Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "accountSubscribe");
+
+            let response = json!({"jsonrpc": "2.0", "result": 7, "id": request["id"]});
+            ws.send(Message::Text(response.to_string())).await.unwrap();
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "accountNotification",
+                "params": {
+                    "result": {
+                        "context": {"slot": 42},
+                        "value": {
+                            "lamports": 1,
+                            "data": ["AQID", "base64"],
+                            "owner": owner.to_string(),
+                            "executable": false,
+                            "rentEpoch": 0
+                        }
+                    },
+                    "subscription": 7
+                }
+            });
+            ws.send(Message::Text(notification.to_string())).await.unwrap();
+
+            let _ = disconnect.await;
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn streams_notifications_until_disconnected() {
+        let (pool, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (disconnect_tx, disconnect_rx) = oneshot::channel();
+        let url = mock_pubsub_server(owner, disconnect_rx).await;
+
+        let (sender, mut events) = mpsc::channel(8);
+        let (_commands_tx, mut commands) = mpsc::channel(8);
+        let conf = AccountDataReceiverConf {
+            stream_name: "accounts-0".into(),
+            sender,
+            source: StreamSource::WebSocket { url: url.clone() },
+        };
+        let mut state = StreamState::default();
+        state
+            .filters
+            .apply(SubscriptionCommand::Set { filter_name: "pool".into(), account_addresses: vec![pool] });
+
+        let statuses = ConnectionStatuses::default();
+        let receiver =
+            tokio::spawn(async move { get_account_data(&conf, &url, &mut state, &mut commands, &statuses).await });
+
+        let Some(StreamEvent::Account(update)) = events.recv().await else {
+            panic!("expected an account update");
+        };
+        assert_eq!((update.pubkey, update.owner, update.slot), (pool, owner, 42));
+        assert_eq!(update.data, vec![1, 2, 3]);
+
+        disconnect_tx.send(()).unwrap();
+        assert!(receiver.await.unwrap().is_err());
+    }
+}
@@ -1,72 +1,373 @@
+use crate::config::{GrpcEndpoint, Rpc};
+use crate::rpc::subscription::{AccountDataReceiverConf, AccountFilters, StreamState, SubscriptionCommand};
+use crate::rpc::supervisor::{ConnectionState, ConnectionStatuses};
+use crate::rpc::{AccountUpdate, ExecutedInstruction, StreamEvent, TransactionUpdate};
+use anyhow::{bail, Context};
+use chrono::Utc;
 use futures::{SinkExt, StreamExt};
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 use std::collections::HashMap;
-use tokio::sync::mpsc::Sender;
-use tracing::{debug, info};
+use std::time::Duration;
+use tokio::fs::read;
+use tokio::select;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::{interval_at, Instant};
+use tonic::transport::{Certificate, ClientTlsConfig};
+use tracing::{debug, info, warn};
 use yellowstone_grpc_client::GeyserGrpcClient;
-use yellowstone_grpc_proto::geyser::{SubscribeRequest, SubscribeRequestFilterAccounts};
+use yellowstone_grpc_proto::geyser::subscribe_request_filter_accounts_filter::Filter;
+use yellowstone_grpc_proto::geyser::{
+    SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+    SubscribeRequestFilterTransactions, SubscribeRequestPing, SubscribeUpdateTransaction,
+};
 use yellowstone_grpc_proto::prelude::subscribe_update::UpdateOneof;
 
-type AccountAddressAndData = (Pubkey, Vec<u8>);
+#[derive(Clone, Debug)]
+pub struct ConnectionConf {
+    pub connect_timeout: Option<Duration>,
+    pub request_timeout: Option<Duration>,
+    pub ping_interval: Duration,
+}
 
-pub struct AccountDataReceiverConf {
-    pub account_address: Pubkey,
-    pub sender: Sender<AccountAddressAndData>,
-    pub yellowstone_grpc_endpoint: String,
+impl From<&Rpc> for ConnectionConf {
+    fn from(config: &Rpc) -> Self {
+        Self {
+            connect_timeout: config.yellowstone_grpc_connect_timeout_secs.map(Duration::from_secs),
+            request_timeout: config.yellowstone_grpc_request_timeout_secs.map(Duration::from_secs),
+            ping_interval: Duration::from_secs(config.yellowstone_grpc_ping_interval_secs.max(1)),
+        }
+    }
 }
 
-pub async fn get_account_data(conf: AccountDataReceiverConf) -> anyhow::Result<()> {
-    let subscribe_req = SubscribeRequest {
-        accounts: HashMap::from_iter(vec![(
-            "".to_string(),
-            SubscribeRequestFilterAccounts {
-                account: vec![String::from(&conf.account_address.clone().to_string())],
+impl AccountFilters {
+    pub fn ping_request(&self, id: i32) -> SubscribeRequest {
+        // The filters are sent along in case the endpoint treats a ping as a new subscription.
+        SubscribeRequest { ping: Some(SubscribeRequestPing { id }), ..self.subscribe_request() }
+    }
+
+    /// Yellowstone replaces the whole subscription of a stream with every request, so all filters are always sent.
+    pub fn subscribe_request(&self) -> SubscribeRequest {
+        let account_filters = self.filters().map(|(filter_name, account_addresses)| {
+            let filter = SubscribeRequestFilterAccounts {
+                account: account_addresses.iter().map(Pubkey::to_string).collect(),
                 owner: vec![],
                 filters: vec![],
-            },
-        )]),
-        slots: Default::default(),
-        transactions: Default::default(),
-        blocks: Default::default(),
-        blocks_meta: Default::default(),
-        entry: Default::default(),
-        commitment: None,
-        accounts_data_slice: vec![],
-        ping: None,
-    };
+            };
+            (filter_name.clone(), filter)
+        });
+        let program_filters = self.programs().map(|(filter_name, program)| {
+            let data_size = SubscribeRequestFilterAccountsFilter { filter: Some(Filter::Datasize(program.data_size)) };
+            let filter = SubscribeRequestFilterAccounts {
+                account: vec![],
+                owner: vec![program.owner.to_string()],
+                filters: vec![data_size],
+            };
+            (filter_name.clone(), filter)
+        });
+        let transaction_filters = self.transactions().map(|(filter_name, transactions)| {
+            let filter = SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                failed: Some(false),
+                signature: None,
+                account_include: transactions.accounts.iter().map(Pubkey::to_string).collect(),
+                account_exclude: vec![],
+                account_required: vec![],
+            };
+            (filter_name.clone(), filter)
+        });
 
-    let mut client = GeyserGrpcClient::connect::<_, String>(conf.yellowstone_grpc_endpoint, None, None)?;
+        SubscribeRequest {
+            accounts: account_filters.chain(program_filters).collect(),
+            slots: Default::default(),
+            transactions: transaction_filters.collect(),
+            blocks: Default::default(),
+            blocks_meta: Default::default(),
+            entry: Default::default(),
+            commitment: None,
+            accounts_data_slice: vec![],
+            ping: None,
+        }
+    }
+}
+
+/// Streams account updates and transactions of all filters until the stream fails or the receiver is dropped.
+///
+/// Commands received meanwhile are applied to the filters and re-sent on the live stream.
+/// `yellowstone-grpc-proto` 1.11 can't request a replay from a slot, so a resubscription is only announced
+/// with `StreamEvent::Resubscribed` and the receiver has to catch up by itself.
+pub async fn get_account_data(
+    conf: &AccountDataReceiverConf,
+    endpoint: &GrpcEndpoint,
+    connection: &ConnectionConf,
+    state: &mut StreamState,
+    commands: &mut Receiver<SubscriptionCommand>,
+    statuses: &ConnectionStatuses,
+) -> anyhow::Result<()> {
+    let mut client = GeyserGrpcClient::connect_with_timeout(
+        endpoint.url.clone(),
+        endpoint.x_token.clone(),
+        tls_config(endpoint).await?,
+        connection.connect_timeout,
+        connection.request_timeout,
+        false,
+    )
+    .await?;
     let (mut sink, mut stream) = client.subscribe().await?;
 
-    let account_address = conf.account_address.clone().to_string();
-    let send = async move {
-        sink.send(subscribe_req.clone())
-            .await
-            .inspect(|_| info!("Subscribed to pool: {}", account_address))?;
+    sink.send(state.filters.subscribe_request()).await?;
+    info!(
+        "Subscribed to accounts of '{stream_name}' at {url}",
+        stream_name = conf.stream_name,
+        url = endpoint.url
+    );
+    statuses
+        .set(&conf.stream_name, ConnectionState::Connected { endpoint: endpoint.url.clone(), since: Utc::now() })
+        .await;
 
-        Ok::<(), anyhow::Error>(())
-    };
+    if state.subscribed_before {
+        let event = StreamEvent::Resubscribed { stream_name: conf.stream_name.clone(), last_slot: state.last_slot };
+        if conf.sender.send(event).await.is_err() {
+            return Ok(());
+        }
+    }
+    state.subscribed_before = true;
 
-    let account_address = conf.account_address.clone();
-    let receive = async move {
-        while let Some(msg) = stream.next().await.transpose().ok().flatten() {
-            if let Some(UpdateOneof::Account(subscribe_update)) = msg.update_oneof {
-                debug!("Received update {account_address}", account_address = account_address.to_string());
+    let ping_every = connection.ping_interval;
+    let mut ping_interval = interval_at(Instant::now() + ping_every, ping_every);
+    let (mut last_ping_id, mut awaited_pong) = (0, None);
 
-                let Some(account_info) = subscribe_update.account else {
+    loop {
+        let msg = select! {
+            Some(command) = commands.recv() => {
+                debug!("Updating subscription of '{stream_name}': {command:?}", stream_name = conf.stream_name);
+                state.filters.apply(command);
+                sink.send(state.filters.subscribe_request()).await?;
+                continue;
+            }
+            _ = ping_interval.tick() => {
+                if let Some(ping_id) = awaited_pong {
+                    bail!("no pong for ping {ping_id} within {ping_every:?}");
+                }
+
+                last_ping_id += 1;
+                sink.send(state.filters.ping_request(last_ping_id)).await?;
+                awaited_pong = Some(last_ping_id);
+                continue;
+            }
+            msg = stream.next() => msg,
+        };
+        let Some(msg) = msg else {
+            bail!("stream closed by the server");
+        };
+
+        let subscribe_update = match msg?.update_oneof {
+            Some(UpdateOneof::Account(subscribe_update)) => subscribe_update,
+            Some(UpdateOneof::Transaction(subscribe_update)) => {
+                let Some(transaction) = transaction_update(subscribe_update) else {
+                    warn!("Received malformed transaction for {}", conf.stream_name);
                     continue;
                 };
+                debug!("Received transaction {signature}", signature = transaction.signature);
 
-                conf.sender
-                    .send((account_address.clone(), account_info.data))
-                    .await
-                    .ok();
+                if conf.sender.send(StreamEvent::Transaction(transaction)).await.is_err() {
+                    return Ok(());
+                }
+                continue;
             }
+            Some(UpdateOneof::Pong(pong)) => {
+                if awaited_pong == Some(pong.id) {
+                    awaited_pong = None;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(account_info) = subscribe_update.account else {
+            continue;
+        };
+
+        let (Ok(account_address), Ok(owner)) =
+            (Pubkey::try_from(account_info.pubkey.as_slice()), Pubkey::try_from(account_info.owner.as_slice()))
+        else {
+            warn!("Received malformed account update for {}", conf.stream_name);
+            continue;
+        };
+        debug!("Received update {account_address}", account_address = account_address.to_string());
+
+        let update = AccountUpdate {
+            pubkey: account_address,
+            owner,
+            data: account_info.data,
+            slot: subscribe_update.slot,
+            write_version: account_info.write_version,
+        };
+        state.last_slot = state.last_slot.max(Some(update.slot));
+        if conf.sender.send(StreamEvent::Account(update)).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Resolves the programs and accounts of the instructions, `None` when the transaction is malformed.
+fn transaction_update(subscribe_update: SubscribeUpdateTransaction) -> Option<TransactionUpdate> {
+    let transaction_info = subscribe_update.transaction?;
+    let message = transaction_info.transaction?.message?;
+    let meta = transaction_info.meta?;
+
+    let account_keys = message
+        .account_keys
+        .iter()
+        .chain(&meta.loaded_writable_addresses)
+        .chain(&meta.loaded_readonly_addresses)
+        .map(|key| Pubkey::try_from(key.as_slice()).ok())
+        .collect::<Option<Vec<_>>>()?;
+    let resolve = |program_id_index: u32, accounts: &[u8], data: Vec<u8>| {
+        Some(ExecutedInstruction {
+            program_id: *account_keys.get(program_id_index as usize)?,
+            accounts: accounts
+                .iter()
+                .map(|index| account_keys.get(*index as usize).copied())
+                .collect::<Option<_>>()?,
+            data,
+        })
+    };
+
+    let mut inner_instructions = meta
+        .inner_instructions
+        .into_iter()
+        .map(|inner| (inner.index, inner.instructions))
+        .collect::<HashMap<_, _>>();
+    let mut instructions = vec![];
+    for (index, instruction) in message.instructions.into_iter().enumerate() {
+        instructions.push(resolve(instruction.program_id_index, &instruction.accounts, instruction.data)?);
+        for inner in inner_instructions.remove(&(index as u32)).unwrap_or_default() {
+            instructions.push(resolve(inner.program_id_index, &inner.accounts, inner.data)?);
         }
+    }
+
+    Some(TransactionUpdate {
+        signature: Signature::try_from(transaction_info.signature.as_slice()).ok()?,
+        slot: subscribe_update.slot,
+        account_keys,
+        instructions,
+        log_messages: meta.log_messages,
+    })
+}
+
+async fn tls_config(endpoint: &GrpcEndpoint) -> anyhow::Result<Option<ClientTlsConfig>> {
+    if endpoint.tls_ca_certificate_path.is_none() && endpoint.tls_domain_name.is_none() {
+        return Ok(None);
+    }
 
-        Ok::<(), anyhow::Error>(())
+    let mut tls_config = ClientTlsConfig::new();
+    if let Some(path) = &endpoint.tls_ca_certificate_path {
+        let pem = read(path)
+            .await
+            .with_context(|| format!("Failed to read CA certificate '{path}'"))?;
+        tls_config = tls_config.ca_certificate(Certificate::from_pem(pem));
+    }
+    if let Some(domain_name) = &endpoint.tls_domain_name {
+        tls_config = tls_config.domain_name(domain_name);
+    }
+
+    Ok(Some(tls_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::subscription::{ProgramFilter, TransactionFilter};
+    use yellowstone_grpc_proto::prelude::{
+        CompiledInstruction, InnerInstruction, InnerInstructions, Message, SubscribeUpdateTransactionInfo, Transaction,
+        TransactionStatusMeta,
     };
 
-    futures::try_join!(send, receive).ok();
-    Ok(())
+    #[test]
+    fn commands_replace_and_remove_filters() {
+        let (pool_a, pool_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut filters = AccountFilters::default();
+
+        filters.apply(SubscriptionCommand::Set { filter_name: "a".into(), account_addresses: vec![pool_a] });
+        filters.apply(SubscriptionCommand::Set { filter_name: "b".into(), account_addresses: vec![pool_b] });
+        filters.apply(SubscriptionCommand::Set { filter_name: "a".into(), account_addresses: vec![pool_a, pool_b] });
+        filters.apply(SubscriptionCommand::Remove { filter_name: "b".into() });
+
+        let request = filters.subscribe_request();
+        assert_eq!(request.accounts.len(), 1);
+        assert_eq!(request.accounts["a"].account, vec![pool_a.to_string(), pool_b.to_string()]);
+    }
+
+    #[test]
+    fn program_filters_select_accounts_by_owner_and_size() {
+        let program = Pubkey::new_unique();
+        let mut filters = AccountFilters::default();
+
+        let filter = ProgramFilter { owner: program, data_size: 752 };
+        filters.apply(SubscriptionCommand::SetProgram { filter_name: "pools".into(), filter });
+
+        let request = filters.subscribe_request();
+        assert_eq!(request.accounts["pools"].owner, vec![program.to_string()]);
+        assert_eq!(request.accounts["pools"].filters[0].filter, Some(Filter::Datasize(752)));
+        assert!(request.accounts["pools"].account.is_empty());
+    }
+
+    #[test]
+    fn transaction_filters_exclude_votes_and_failures() {
+        let program = Pubkey::new_unique();
+        let mut filters = AccountFilters::default();
+
+        let filter = TransactionFilter { accounts: vec![program] };
+        filters.apply(SubscriptionCommand::SetTransactions { filter_name: "swaps".into(), filter });
+
+        let request = filters.subscribe_request();
+        let swaps = &request.transactions["swaps"];
+        assert_eq!(swaps.account_include, vec![program.to_string()]);
+        assert_eq!((swaps.vote, swaps.failed), (Some(false), Some(false)));
+        assert!(request.accounts.is_empty());
+    }
+
+    #[test]
+    fn resolves_inner_instructions_and_loaded_addresses() {
+        let (payer, program, inner_program, loaded) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let signature = Signature::new_unique();
+
+        let message = Message {
+            account_keys: [payer, program, inner_program]
+                .iter()
+                .map(|key| key.to_bytes().to_vec())
+                .collect(),
+            instructions: vec![CompiledInstruction { program_id_index: 1, accounts: vec![0, 3], data: vec![9] }],
+            ..Default::default()
+        };
+        let inner_instruction =
+            InnerInstruction { program_id_index: 2, accounts: vec![3], data: vec![3], stack_height: Some(2) };
+        let meta = TransactionStatusMeta {
+            inner_instructions: vec![InnerInstructions { index: 0, instructions: vec![inner_instruction] }],
+            log_messages: vec!["Program log: hello".into()],
+            loaded_writable_addresses: vec![loaded.to_bytes().to_vec()],
+            ..Default::default()
+        };
+        let transaction_info = SubscribeUpdateTransactionInfo {
+            signature: signature.as_ref().to_vec(),
+            transaction: Some(Transaction { signatures: vec![signature.as_ref().to_vec()], message: Some(message) }),
+            meta: Some(meta),
+            ..Default::default()
+        };
+
+        let transaction =
+            transaction_update(SubscribeUpdateTransaction { transaction: Some(transaction_info), slot: 42 }).unwrap();
+
+        assert_eq!((transaction.signature, transaction.slot), (signature, 42));
+        assert_eq!(transaction.account_keys, vec![payer, program, inner_program, loaded]);
+        assert_eq!(
+            transaction.instructions,
+            vec![
+                ExecutedInstruction { program_id: program, accounts: vec![payer, loaded], data: vec![9] },
+                ExecutedInstruction { program_id: inner_program, accounts: vec![loaded], data: vec![3] },
+            ]
+        );
+        assert_eq!(transaction.log_messages, vec!["Program log: hello".to_string()]);
+    }
 }
@@ -1,7 +1,9 @@
+pub mod memory;
 pub mod postgres;
 
-use crate::models::PoolAndPrice;
+use crate::models::{PoolAndPrice, Price, PriceGap, Trade};
 use async_trait::async_trait;
+pub use memory::MemoryStorage;
 pub use postgres::PostgresStorage;
 use solana_sdk::pubkey::Pubkey;
 use std::time::Duration;
@@ -9,8 +11,11 @@ use std::time::Duration;
 #[async_trait]
 pub trait PoolPriceStorage {
     async fn save(&self, price_for_pool: &PoolAndPrice) -> anyhow::Result<Pubkey>;
-    async fn average(&self, pool_pubkey: &Pubkey, for_period: Duration) -> anyhow::Result<f64>;
-    async fn current(&self, pool_pubkey: &Pubkey) -> anyhow::Result<f64>;
+    async fn average(&self, pool_pubkey: &Pubkey, for_period: Duration) -> anyhow::Result<Option<Price>>;
+    async fn current(&self, pool_pubkey: &Pubkey) -> anyhow::Result<Option<Price>>;
+    async fn save_gap(&self, gap: &PriceGap) -> anyhow::Result<()>;
+    /// Saving a trade that is already stored does nothing.
+    async fn save_trade(&self, trade: &Trade) -> anyhow::Result<()>;
 }
 
 #[async_trait]
@@ -0,0 +1,82 @@
+use crate::models::{PoolAndPrice, Price, PriceGap, Trade};
+use crate::storage::{OldRecordCleaner, PoolPriceStorage};
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Keeps the records in memory, replays are saved here so they never mix with the prices in the database.
+#[derive(Default)]
+pub struct MemoryStorage {
+    prices: Mutex<Vec<PoolAndPrice>>,
+    gaps: Mutex<Vec<PriceGap>>,
+    trades: Mutex<Vec<Trade>>,
+}
+
+impl MemoryStorage {
+    /// Saved prices in the order they were saved in.
+    pub async fn prices(&self) -> Vec<PoolAndPrice> {
+        self.prices.lock().await.clone()
+    }
+}
+
+#[async_trait]
+impl PoolPriceStorage for MemoryStorage {
+    async fn save(&self, price_for_pool: &PoolAndPrice) -> anyhow::Result<Pubkey> {
+        self.prices.lock().await.push(price_for_pool.clone());
+        Ok(price_for_pool.pool_pubkey)
+    }
+
+    async fn average(&self, pool_pubkey: &Pubkey, for_period: Duration) -> anyhow::Result<Option<Price>> {
+        let since = Utc::now().naive_utc() - chrono::Duration::from_std(for_period)?;
+        let prices = self
+            .prices
+            .lock()
+            .await
+            .iter()
+            .filter(|price| price.pool_pubkey == *pool_pubkey && price.updated_at >= since)
+            .map(|price| BigDecimal::from(price.price.clone()))
+            .collect::<Vec<_>>();
+        if prices.is_empty() {
+            return Ok(None);
+        }
+
+        let count = BigDecimal::from(prices.len() as u64);
+        Ok(Some(Price::new(prices.into_iter().sum::<BigDecimal>() / count)))
+    }
+
+    async fn current(&self, pool_pubkey: &Pubkey) -> anyhow::Result<Option<Price>> {
+        let prices = self.prices.lock().await;
+        Ok(prices
+            .iter()
+            .rev()
+            .find(|price| price.pool_pubkey == *pool_pubkey)
+            .map(|price| price.price.clone()))
+    }
+
+    async fn save_gap(&self, gap: &PriceGap) -> anyhow::Result<()> {
+        self.gaps.lock().await.push(gap.clone());
+        Ok(())
+    }
+
+    async fn save_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        let mut trades = self.trades.lock().await;
+        let is_stored = trades
+            .iter()
+            .any(|stored| stored.signature == trade.signature && stored.instruction_index == trade.instruction_index);
+        if !is_stored {
+            trades.push(trade.clone());
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OldRecordCleaner for MemoryStorage {
+    async fn clear_old_records(&self, _in_interval: Duration) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
 use crate::config::Settings;
-use crate::models::PoolAndPrice;
+use crate::models::{PoolAndPrice, Price, PriceGap, Trade};
 use crate::storage::{OldRecordCleaner, PoolPriceStorage};
 use crate::trait_ext::duration_ext::DurationExt;
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
+use bigdecimal::BigDecimal;
 use solana_sdk::pubkey::Pubkey;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{query, PgPool, Row};
@@ -24,15 +25,17 @@ impl PoolPriceStorage for PostgresStorage {
             (
                 pool_pk,
                 price,
+                slot,
                 updated_at
             )
-            VALUES ($1, $2, $3)
+            VALUES ($1, $2, $3, $4)
             RETURNING pool_pk
         "#;
 
         let pool_pk = query(sql)
             .bind(price_and_pool.pool_pubkey.to_bytes())
-            .bind(price_and_pool.price)
+            .bind(price_and_pool.price.clone())
+            .bind(i64::try_from(price_and_pool.slot).context("Slot does not fit into BIGINT")?)
             .bind(price_and_pool.updated_at)
             .fetch_one(&self.pg_pool)
             .await?
@@ -44,7 +47,7 @@ impl PoolPriceStorage for PostgresStorage {
             .context("Cannot parse pool_pk when insert")
     }
 
-    async fn average(&self, pool_pubkey: &Pubkey, for_interval: Duration) -> anyhow::Result<f64> {
+    async fn average(&self, pool_pubkey: &Pubkey, for_interval: Duration) -> anyhow::Result<Option<Price>> {
         let sql = r#"
             SELECT AVG(price)
             FROM raydium_pools_prices
@@ -54,20 +57,15 @@ impl PoolPriceStorage for PostgresStorage {
                 pool_pk = $2
         "#;
 
-        let Some(average_value) = query(sql)
+        Ok(query(sql)
             .bind(for_interval)
             .bind(pool_pubkey.to_bytes())
             .fetch_one(&self.pg_pool)
             .await?
-            .try_get::<Option<f64>, _>("avg")?
-        else {
-            return Ok(-1.0);
-        };
-
-        Ok(average_value)
+            .try_get::<Option<Price>, _>("avg")?)
     }
 
-    async fn current(&self, pool_pubkey: &Pubkey) -> anyhow::Result<f64> {
+    async fn current(&self, pool_pubkey: &Pubkey) -> anyhow::Result<Option<Price>> {
         let sql = r#"
             SELECT price
             FROM raydium_pools_prices
@@ -76,16 +74,77 @@ impl PoolPriceStorage for PostgresStorage {
             LIMIT 1
         "#;
 
-        let Some(average) = query(sql)
+        let Some(current) = query(sql)
             .bind(pool_pubkey.to_bytes())
             .fetch_optional(&self.pg_pool)
             .await?
         else {
-            return Ok(-1.0)
+            return Ok(None);
         };
 
-        Ok(average.try_get::<_, _>("price").context("Failed to get 'price'")?)
+        Ok(Some(current.try_get::<_, _>("price").context("Failed to get 'price'")?))
+    }
+
+    async fn save_gap(&self, gap: &PriceGap) -> anyhow::Result<()> {
+        let sql = r#"
+            INSERT INTO raydium_pools_price_gaps
+            (
+                pool_pk,
+                from_slot,
+                to_slot,
+                detected_at
+            )
+            VALUES ($1, $2, $3, $4)
+        "#;
+
+        query(sql)
+            .bind(gap.pool_pubkey.to_bytes())
+            .bind(
+                gap.from_slot
+                    .map(i64::try_from)
+                    .transpose()
+                    .context("Slot does not fit into BIGINT")?,
+            )
+            .bind(i64::try_from(gap.to_slot).context("Slot does not fit into BIGINT")?)
+            .bind(gap.detected_at)
+            .execute(&self.pg_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn save_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        let sql = r#"
+            INSERT INTO raydium_pools_trades
+            (
+                signature,
+                instruction_index,
+                pool_pk,
+                slot,
+                direction,
+                amount_in,
+                amount_out,
+                user_pk,
+                recorded_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (signature, instruction_index) DO NOTHING
+        "#;
+
+        query(sql)
+            .bind(trade.signature.as_ref())
+            .bind(i32::try_from(trade.instruction_index).context("Instruction index does not fit into INTEGER")?)
+            .bind(trade.pool_pubkey.to_bytes())
+            .bind(i64::try_from(trade.slot).context("Slot does not fit into BIGINT")?)
+            .bind(trade.direction.as_str())
+            .bind(BigDecimal::from(trade.amount_in))
+            .bind(BigDecimal::from(trade.amount_out))
+            .bind(trade.user.to_bytes())
+            .bind(trade.recorded_at)
+            .execute(&self.pg_pool)
+            .await?;
 
+        Ok(())
     }
 }
 
@@ -164,20 +223,23 @@ impl From<PgPool> for PostgresStorage {
 
 #[cfg(test)]
 mod test {
-    use crate::models::PoolAndPrice;
+    use crate::models::{PoolAndPrice, Price, PriceGap, Trade, TradeDirection};
     use crate::storage::{PoolPriceStorage, PostgresStorage};
     use crate::trait_ext::duration_ext::DurationExt;
+    use bigdecimal::num_bigint::BigInt;
+    use bigdecimal::BigDecimal;
     use chrono::Utc;
     use rand::Rng;
     use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signature;
     use sqlx::postgres::PgPoolOptions;
     use sqlx::PgPool;
     use std::thread::sleep;
     use std::time::Duration;
 
     const PUBLIC_KEY_OF_POOL: &str = "EP2ib6dYdEeqD8MfE2ezHCxX3kP3K2eLKkirfPm5eyMx";
-    const MIN_PRICE: f64 = 100.0;
-    const MAX_PRICE: f64 = 100_000.00;
+    const MIN_PRICE_IN_CENTS: u64 = 100_00;
+    const MAX_PRICE_IN_CENTS: u64 = 100_000_00;
     const AMOUNT_OF_TEST_RECORDS: usize = 10;
 
     async fn get_pg_pool() -> PgPool {
@@ -189,15 +251,21 @@ mod test {
             .expect("Failed to connect to Postgres")
     }
 
-    fn get_random_price() -> f64 {
-        rand::thread_rng().gen_range(MIN_PRICE..=MAX_PRICE)
+    fn get_random_price() -> Price {
+        let cents = rand::thread_rng().gen_range(MIN_PRICE_IN_CENTS..=MAX_PRICE_IN_CENTS);
+        Price::new(BigDecimal::new(BigInt::from(cents), 2))
     }
 
     fn create_test_models() -> Vec<PoolAndPrice> {
         let mut res = Vec::with_capacity(AMOUNT_OF_TEST_RECORDS);
-        for _ in 0..AMOUNT_OF_TEST_RECORDS {
+        for slot in 0..AMOUNT_OF_TEST_RECORDS as u64 {
             sleep(Duration::from_millis(100));
-            res.push(PoolAndPrice::new(Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap(), get_random_price(), Utc::now().naive_utc()))
+            res.push(PoolAndPrice::new(
+                Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap(),
+                get_random_price(),
+                slot,
+                Utc::now().naive_utc(),
+            ))
         }
 
         res
@@ -213,6 +281,45 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn save_gap_test() {
+        let storage = PostgresStorage::refresh_table(get_pg_pool().await)
+            .await
+            .expect("Failed to fresh table");
+        let gap = PriceGap::new(Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap(), Some(100), 150, Utc::now().naive_utc());
+
+        assert!(storage.save_gap(&gap).await.inspect_err(|x| eprintln!("{x}")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn save_trade_test() {
+        let storage = PostgresStorage::refresh_table(get_pg_pool().await)
+            .await
+            .expect("Failed to fresh table");
+        let trade = Trade {
+            signature: Signature::new_unique(),
+            instruction_index: 2,
+            pool_pubkey: Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap(),
+            slot: 150,
+            direction: TradeDirection::PcToCoin,
+            amount_in: 1_000_000_000,
+            amount_out: u64::MAX,
+            user: Pubkey::new_unique(),
+            recorded_at: Utc::now().naive_utc(),
+        };
+
+        assert!(storage
+            .save_trade(&trade)
+            .await
+            .inspect_err(|x| eprintln!("{x}"))
+            .is_ok());
+        assert!(storage
+            .save_trade(&trade)
+            .await
+            .inspect_err(|x| eprintln!("{x}"))
+            .is_ok());
+    }
+
     #[tokio::test]
     async fn average_test() {
         let storage = PostgresStorage::refresh_table(get_pg_pool().await)
@@ -220,8 +327,13 @@ mod test {
             .expect("Failed to fresh table");
 
         let records_for_test = create_test_models();
-        let expected_average =
-            records_for_test.iter().map(|model| model.price).sum::<f64>() / records_for_test.len() as f64;
+        let expected_average = Price::new(
+            records_for_test
+                .iter()
+                .map(|model| model.price.clone().into())
+                .sum::<BigDecimal>()
+                / BigDecimal::from(records_for_test.len() as u64),
+        );
 
         for model in records_for_test {
             assert!(storage.save(&model).await.inspect_err(|x| eprintln!("{x}")).is_ok());
@@ -231,7 +343,7 @@ mod test {
             .average(&Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap(), Duration::from_minutes(5))
             .await
             .expect("Unable to average");
-        assert_eq!(expected_average, actual_average);
+        assert_eq!(Some(expected_average), actual_average);
     }
 
     #[tokio::test]
@@ -241,12 +353,19 @@ mod test {
             .expect("Failed to fresh table");
 
         let records_for_test = create_test_models();
-        let expected_current = records_for_test.last().expect("Unable to get last record").price;
+        let expected_current = records_for_test
+            .last()
+            .expect("Unable to get last record")
+            .price
+            .clone();
         for model in records_for_test {
             assert!(storage.save(&model).await.inspect_err(|x| eprintln!("{x}")).is_ok());
         }
 
-        let actual_current = storage.current(&Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap()).await.expect("Unable to average");
-        assert_eq!(expected_current, actual_current);
+        let actual_current = storage
+            .current(&Pubkey::try_from(PUBLIC_KEY_OF_POOL).unwrap())
+            .await
+            .expect("Unable to average");
+        assert_eq!(Some(expected_current), actual_current);
     }
 }